@@ -0,0 +1,35 @@
+use contact::jsonrpc::client::decode_response;
+use contact::types::{LatestBlockEndpointResponse, TxSendErrorResponse};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde_json::{from_value, Value};
+use std::fs::read;
+
+/// How responses were decoded before decode_response, into a Value that is
+/// cloned for the attempt at the expected type and reused for the error shape
+fn decode_via_value(body: &[u8]) -> Option<LatestBlockEndpointResponse> {
+    let json: Value = serde_json::from_slice(body).ok()?;
+    match from_value(json.clone()) {
+        Ok(val) => Some(val),
+        Err(_) => {
+            let _: Option<TxSendErrorResponse> = from_value(json).ok();
+            None
+        }
+    }
+}
+
+fn decode_block(c: &mut Criterion) {
+    let body =
+        read("test_files/test_block_althea_testnet1v5.json").expect("Failed to read test files!");
+
+    let mut group = c.benchmark_group("decode_block");
+    group.bench_function("via_value", |b| {
+        b.iter(|| decode_via_value(black_box(&body)).unwrap())
+    });
+    group.bench_function("decode_response", |b| {
+        b.iter(|| decode_response::<LatestBlockEndpointResponse>(black_box(&body)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, decode_block);
+criterion_main!(benches);
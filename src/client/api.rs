@@ -0,0 +1,144 @@
+use crate::client::Contact;
+use crate::jsonrpc::error::JsonRpcError;
+use crate::types::*;
+use async_trait::async_trait;
+use deep_space::address::Address;
+use deep_space::coin::Coin;
+use deep_space::msg::Msg;
+use deep_space::private_key::PrivateKey;
+use deep_space::transaction::Transaction;
+
+/// The queries and sends Contact provides, code that depends on this trait
+/// rather than on Contact can be tested against MockContact without a node
+#[cfg_attr(feature = "actix", async_trait(?Send))]
+#[cfg_attr(not(feature = "actix"), async_trait)]
+pub trait CosmosClient {
+    async fn get_latest_block_number(&self) -> Result<u64, JsonRpcError>;
+
+    async fn get_latest_block(&self) -> Result<LatestBlockEndpointResponse, JsonRpcError>;
+
+    async fn get_block(&self, height: u64) -> Result<Block, JsonRpcError>;
+
+    async fn get_validator_set(
+        &self,
+        height: u64,
+    ) -> Result<ResponseWrapper<ValidatorSet>, JsonRpcError>;
+
+    async fn get_chain_id(&self) -> Result<String, JsonRpcError>;
+
+    async fn get_node_info(&self) -> Result<NodeInfoResponse, JsonRpcError>;
+
+    async fn get_syncing_status(&self) -> Result<SyncingStatus, JsonRpcError>;
+
+    async fn get_account_info(
+        &self,
+        address: Address,
+    ) -> Result<ResponseWrapper<TypeWrapper<Option<CosmosAccountInfo>>>, JsonRpcError>;
+
+    async fn get_account_number(&self, address: Address) -> Result<u64, JsonRpcError>;
+
+    async fn get_tx_by_hash(&self, txhash: &str) -> Result<TXSendResponse, JsonRpcError>;
+
+    async fn get_balances(
+        &self,
+        address: Address,
+    ) -> Result<ResponseWrapper<Vec<Coin>>, JsonRpcError>;
+
+    async fn send_transaction(&self, tx: Transaction<Msg>) -> Result<TXSendResponse, JsonRpcError>;
+
+    #[allow(clippy::too_many_arguments)]
+    async fn create_and_send_transaction(
+        &self,
+        coin: Coin,
+        fee: Coin,
+        destination: Address,
+        private_key: PrivateKey,
+        chain_id: Option<String>,
+        account_number: Option<u64>,
+        sequence: Option<u64>,
+    ) -> Result<TXSendResponse, JsonRpcError>;
+}
+
+#[cfg_attr(feature = "actix", async_trait(?Send))]
+#[cfg_attr(not(feature = "actix"), async_trait)]
+impl CosmosClient for Contact {
+    async fn get_latest_block_number(&self) -> Result<u64, JsonRpcError> {
+        Contact::get_latest_block_number(self).await
+    }
+
+    async fn get_latest_block(&self) -> Result<LatestBlockEndpointResponse, JsonRpcError> {
+        Contact::get_latest_block(self).await
+    }
+
+    async fn get_block(&self, height: u64) -> Result<Block, JsonRpcError> {
+        Contact::get_block(self, height).await
+    }
+
+    async fn get_validator_set(
+        &self,
+        height: u64,
+    ) -> Result<ResponseWrapper<ValidatorSet>, JsonRpcError> {
+        Contact::get_validator_set(self, height).await
+    }
+
+    async fn get_chain_id(&self) -> Result<String, JsonRpcError> {
+        Contact::get_chain_id(self).await
+    }
+
+    async fn get_node_info(&self) -> Result<NodeInfoResponse, JsonRpcError> {
+        Contact::get_node_info(self).await
+    }
+
+    async fn get_syncing_status(&self) -> Result<SyncingStatus, JsonRpcError> {
+        Contact::get_syncing_status(self).await
+    }
+
+    async fn get_account_info(
+        &self,
+        address: Address,
+    ) -> Result<ResponseWrapper<TypeWrapper<Option<CosmosAccountInfo>>>, JsonRpcError> {
+        Contact::get_account_info(self, address).await
+    }
+
+    async fn get_account_number(&self, address: Address) -> Result<u64, JsonRpcError> {
+        Contact::get_account_number(self, address).await
+    }
+
+    async fn get_tx_by_hash(&self, txhash: &str) -> Result<TXSendResponse, JsonRpcError> {
+        Contact::get_tx_by_hash(self, txhash).await
+    }
+
+    async fn get_balances(
+        &self,
+        address: Address,
+    ) -> Result<ResponseWrapper<Vec<Coin>>, JsonRpcError> {
+        Contact::get_balances(self, address).await
+    }
+
+    async fn send_transaction(&self, tx: Transaction<Msg>) -> Result<TXSendResponse, JsonRpcError> {
+        Contact::send_transaction(self, tx).await
+    }
+
+    async fn create_and_send_transaction(
+        &self,
+        coin: Coin,
+        fee: Coin,
+        destination: Address,
+        private_key: PrivateKey,
+        chain_id: Option<String>,
+        account_number: Option<u64>,
+        sequence: Option<u64>,
+    ) -> Result<TXSendResponse, JsonRpcError> {
+        Contact::create_and_send_transaction(
+            self,
+            coin,
+            fee,
+            destination,
+            private_key,
+            chain_id,
+            account_number,
+            sequence,
+        )
+        .await
+    }
+}
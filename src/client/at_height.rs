@@ -0,0 +1,142 @@
+use crate::client::Contact;
+use crate::jsonrpc::error::JsonRpcError;
+use crate::types::*;
+use deep_space::{address::Address, coin::Coin};
+use std::collections::HashMap;
+
+/// A read only view of the chain state at a single block height, created
+/// with Contact::at_height
+///
+/// Only queries with an answer that depends on the height are here. Transactions
+/// are looked up by hash and the same at every height, while the chain id, node
+/// info and syncing status describe the node rather than the chain state, so
+/// use the Contact for those
+#[derive(Clone)]
+pub struct AtHeight {
+    contact: Contact,
+}
+
+impl AtHeight {
+    pub(crate) fn new(contact: Contact) -> Self {
+        AtHeight { contact }
+    }
+
+    pub fn height(&self) -> u64 {
+        self.contact.height.unwrap()
+    }
+
+    /// The underlying Contact, pinned to our height
+    pub fn contact(&self) -> &Contact {
+        &self.contact
+    }
+
+    /// The block at our height
+    pub async fn get_block(&self) -> Result<Block, JsonRpcError> {
+        self.contact.get_block(self.height()).await
+    }
+
+    /// The validator set at our height
+    pub async fn get_validator_set(&self) -> Result<ResponseWrapper<ValidatorSet>, JsonRpcError> {
+        self.contact.get_validator_set(self.height()).await
+    }
+
+    pub async fn get_account_info(
+        &self,
+        address: Address,
+    ) -> Result<ResponseWrapper<TypeWrapper<Option<CosmosAccountInfo>>>, JsonRpcError> {
+        self.contact.get_account_info(address).await
+    }
+
+    pub async fn get_account_number(&self, address: Address) -> Result<u64, JsonRpcError> {
+        self.contact.get_account_number(address).await
+    }
+
+    pub async fn get_balances(
+        &self,
+        address: Address,
+    ) -> Result<ResponseWrapper<Vec<Coin>>, JsonRpcError> {
+        self.contact.get_balances(address).await
+    }
+
+    pub async fn get_balances_many(
+        &self,
+        addresses: &[Address],
+        concurrency: usize,
+    ) -> HashMap<Address, Result<ResponseWrapper<Vec<Coin>>, JsonRpcError>> {
+        self.contact.get_balances_many(addresses, concurrency).await
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub async fn get_account_info_many(
+        &self,
+        addresses: &[Address],
+        concurrency: usize,
+    ) -> HashMap<
+        Address,
+        Result<ResponseWrapper<TypeWrapper<Option<CosmosAccountInfo>>>, JsonRpcError>,
+    > {
+        self.contact
+            .get_account_info_many(addresses, concurrency)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonrpc::transport::mock::{test_contact, MockTransport};
+    use actix::System;
+    use deep_space::private_key::PrivateKey;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_at_height() {
+        let address = PrivateKey::from_secret(&[1; 32])
+            .to_public_key()
+            .unwrap()
+            .to_address();
+        let transport = Arc::new(MockTransport::default());
+        transport.respond(
+            &format!("http://a/bank/balances/{}?height=10", address),
+            200,
+            r#"{"height":"10","result":[{"denom":"footoken","amount":"5"}]}"#,
+        );
+        transport.respond(
+            &format!("http://a/bank/balances/{}?height=11", address),
+            200,
+            r#"{"height":"12","result":[]}"#,
+        );
+        transport.respond(
+            "http://a/blocks/489421",
+            200,
+            include_str!("../../test_files/test_block_althea_testnet1v5.json"),
+        );
+        transport.respond(
+            "http://a/validatorsets/489421",
+            200,
+            r#"{"height":"489421","result":{"block_height":"489421","validators":[]}}"#,
+        );
+        let contact = test_contact(&["http://a"], transport.clone());
+
+        System::new("test").block_on(async move {
+            let view = contact.at_height(489421);
+            assert_eq!(view.get_block().await.unwrap().height(), 489421);
+            let validators = view.get_validator_set().await.unwrap();
+            assert_eq!(validators.result.block_height, 489421);
+
+            let view = contact.at_height(10);
+            assert_eq!(view.height(), 10);
+            let balances = view.get_balances(address).await.unwrap();
+            assert_eq!(balances.height, 10);
+            assert_eq!(balances.result[0].amount, 5u32.into());
+
+            match contact.at_height(11).get_balances(address).await {
+                Err(JsonRpcError::HeightMismatch {
+                    requested: 11,
+                    returned: 12,
+                }) => {}
+                res => panic!("Unexpected result {:?}", res),
+            }
+        });
+    }
+}
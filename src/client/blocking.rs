@@ -0,0 +1,437 @@
+use crate::client::{AtHeight, Contact, ContactBuilder};
+use crate::jsonrpc::error::JsonRpcError;
+use crate::jsonrpc::transport::Transport;
+use crate::types::*;
+use deep_space::address::Address;
+use deep_space::coin::Coin;
+use deep_space::private_key::PrivateKey;
+use deep_space::transaction::Transaction;
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+/// When actix is available we need a full actix System for the actix transport
+/// to function, it's a tokio runtime underneath so any other transport works too
+#[cfg(feature = "actix")]
+type Runtime = actix_rt::SystemRunner;
+#[cfg(not(feature = "actix"))]
+type Runtime = tokio::runtime::Runtime;
+
+#[cfg(feature = "actix")]
+fn new_runtime() -> Runtime {
+    actix_rt::System::new("contact")
+}
+
+#[cfg(not(feature = "actix"))]
+fn new_runtime() -> Runtime {
+    tokio::runtime::Builder::new()
+        .basic_scheduler()
+        .enable_all()
+        .build()
+        .expect("Failed to start tokio runtime!")
+}
+
+/// A synchronous version of Contact for callers that don't have an async
+/// runtime of their own. Each instance owns a single threaded runtime and
+/// blocks the calling thread until each request is complete, so it must
+/// not be used from inside another async runtime.
+///
+/// Starting that runtime, an actix System when the actix transport is enabled,
+/// is much more expensive than a request, so create one instance and keep it
+/// rather than one per call. Views from at_height and blocks_from borrow the
+/// instance and run on its runtime.
+pub struct ContactBlocking {
+    contact: Contact,
+    runtime: RefCell<Runtime>,
+}
+
+impl ContactBlocking {
+    pub fn new(url: &str, timeout: Duration) -> Self {
+        let mut runtime = new_runtime();
+        // the actix client has to be created inside of the actix system
+        let url = url.to_string();
+        let contact = runtime.block_on(async move { Contact::new(&url, timeout) });
+        ContactBlocking {
+            contact,
+            runtime: RefCell::new(runtime),
+        }
+    }
+
+    pub fn with_transport<T: Transport + 'static>(
+        url: &str,
+        timeout: Duration,
+        transport: T,
+    ) -> Self {
+        ContactBlocking {
+            contact: Contact::with_transport(url, timeout, transport),
+            runtime: RefCell::new(new_runtime()),
+        }
+    }
+
+    /// Creates an instance using the provided builder, see ContactBuilder::build
+    pub fn from_builder(builder: ContactBuilder) -> Result<Self, JsonRpcError> {
+        let mut runtime = new_runtime();
+        let contact = runtime.block_on(async move { builder.build() })?;
+        Ok(ContactBlocking {
+            contact,
+            runtime: RefCell::new(runtime),
+        })
+    }
+
+    /// Creates an instance and checks that the node is usable, see Contact::connect
+    pub fn connect(
+        url: &str,
+        timeout: Duration,
+        expected_chain_id: Option<&str>,
+    ) -> Result<Self, JsonRpcError> {
+        let mut runtime = new_runtime();
+        let url = url.to_string();
+        let expected_chain_id = expected_chain_id.map(|id| id.to_string());
+        let contact = runtime.block_on(async move {
+            Contact::connect(&url, timeout, expected_chain_id.as_deref()).await
+        })?;
+        Ok(ContactBlocking {
+            contact,
+            runtime: RefCell::new(runtime),
+        })
+    }
+
+    /// The async Contact used by this instance
+    pub fn contact(&self) -> &Contact {
+        &self.contact
+    }
+
+    /// Runs the provided closure over a clone of the Contact on our runtime, the
+    /// actix runtime requires 'static futures so we can't borrow self here
+    fn block_on<F, R>(&self, f: impl FnOnce(Contact) -> F) -> R
+    where
+        F: Future<Output = R> + 'static,
+    {
+        let future = f(self.contact.clone());
+        self.runtime.borrow_mut().block_on(future)
+    }
+
+    /// A read only view of the chain state at `height`, see Contact::at_height
+    pub fn at_height(&self, height: u64) -> AtHeightBlocking<'_> {
+        AtHeightBlocking {
+            blocking: self,
+            height,
+        }
+    }
+
+    /// Every block from `start_height` onwards, following the chain tip once
+    /// it is reached, see Contact::blocks_from. Each call to next blocks until
+    /// the next block is produced, the iterator ends after the first error
+    pub fn blocks_from(
+        &self,
+        start_height: u64,
+    ) -> impl Iterator<Item = Result<Block, JsonRpcError>> + '_ {
+        Blocks {
+            blocking: self,
+            stream: Some(Box::pin(self.contact.blocks_from(start_height))),
+        }
+    }
+
+    pub fn get_latest_block_number(&self) -> Result<u64, JsonRpcError> {
+        self.block_on(|c| async move { c.get_latest_block_number().await })
+    }
+
+    pub fn get_latest_block(&self) -> Result<LatestBlockEndpointResponse, JsonRpcError> {
+        self.block_on(|c| async move { c.get_latest_block().await })
+    }
+
+    pub fn get_chain_id(&self) -> Result<String, JsonRpcError> {
+        self.block_on(|c| async move { c.get_chain_id().await })
+    }
+
+    pub fn get_block(&self, height: u64) -> Result<Block, JsonRpcError> {
+        self.block_on(|c| async move { c.get_block(height).await })
+    }
+
+    pub fn get_validator_set(
+        &self,
+        height: u64,
+    ) -> Result<ResponseWrapper<ValidatorSet>, JsonRpcError> {
+        self.block_on(|c| async move { c.get_validator_set(height).await })
+    }
+
+    pub fn get_latest_validator_set(&self) -> Result<ResponseWrapper<ValidatorSet>, JsonRpcError> {
+        self.block_on(|c| async move { c.get_latest_validator_set().await })
+    }
+
+    pub fn get_node_info(&self) -> Result<NodeInfoResponse, JsonRpcError> {
+        self.block_on(|c| async move { c.get_node_info().await })
+    }
+
+    pub fn get_syncing_status(&self) -> Result<SyncingStatus, JsonRpcError> {
+        self.block_on(|c| async move { c.get_syncing_status().await })
+    }
+
+    pub fn wait_for_chain_start(&self, timeout: Duration) -> Result<u64, JsonRpcError> {
+        self.block_on(|c| async move { c.wait_for_chain_start(timeout).await })
+    }
+
+    pub fn wait_for_sync(&self, timeout: Duration) -> Result<u64, JsonRpcError> {
+        self.block_on(|c| async move { c.wait_for_sync(timeout).await })
+    }
+
+    pub fn wait_for_height(&self, height: u64, timeout: Duration) -> Result<u64, JsonRpcError> {
+        self.block_on(|c| async move { c.wait_for_height(height, timeout).await })
+    }
+
+    pub fn check_endpoints(&self) -> usize {
+        self.block_on(|c| async move { c.check_endpoints().await })
+    }
+
+    pub fn get_account_info(
+        &self,
+        address: Address,
+    ) -> Result<ResponseWrapper<TypeWrapper<Option<CosmosAccountInfo>>>, JsonRpcError> {
+        self.block_on(|c| async move { c.get_account_info(address).await })
+    }
+
+    pub fn get_account_number(&self, address: Address) -> Result<u64, JsonRpcError> {
+        self.block_on(|c| async move { c.get_account_number(address).await })
+    }
+
+    pub fn get_tx_by_hash(&self, txhash: &str) -> Result<TXSendResponse, JsonRpcError> {
+        let txhash = txhash.to_string();
+        self.block_on(|c| async move { c.get_tx_by_hash(&txhash).await })
+    }
+
+    pub fn get_balances(
+        &self,
+        address: Address,
+    ) -> Result<ResponseWrapper<Vec<Coin>>, JsonRpcError> {
+        self.block_on(|c| async move { c.get_balances(address).await })
+    }
+
+    pub fn get_balances_many(
+        &self,
+        addresses: &[Address],
+        concurrency: usize,
+    ) -> HashMap<Address, Result<ResponseWrapper<Vec<Coin>>, JsonRpcError>> {
+        let addresses = addresses.to_vec();
+        self.block_on(|c| async move { c.get_balances_many(&addresses, concurrency).await })
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn get_account_info_many(
+        &self,
+        addresses: &[Address],
+        concurrency: usize,
+    ) -> HashMap<
+        Address,
+        Result<ResponseWrapper<TypeWrapper<Option<CosmosAccountInfo>>>, JsonRpcError>,
+    > {
+        let addresses = addresses.to_vec();
+        self.block_on(|c| async move { c.get_account_info_many(&addresses, concurrency).await })
+    }
+
+    pub fn send_transaction<M: Clone + Serialize + 'static>(
+        &self,
+        msg: Transaction<M>,
+    ) -> Result<TXSendResponse, JsonRpcError> {
+        self.block_on(|c| async move { c.send_transaction(msg).await })
+    }
+
+    pub fn retry_on_block<
+        M: Clone + Serialize + 'static,
+        T: 'static + for<'de> Deserialize<'de> + Debug,
+    >(
+        &self,
+        tx: Transaction<M>,
+    ) -> Result<T, JsonRpcError> {
+        self.block_on(|c| async move { c.retry_on_block(tx).await })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_and_send_transaction(
+        &self,
+        coin: Coin,
+        fee: Coin,
+        destination: Address,
+        private_key: PrivateKey,
+        chain_id: Option<String>,
+        account_number: Option<u64>,
+        sequence: Option<u64>,
+    ) -> Result<TXSendResponse, JsonRpcError> {
+        self.block_on(|c| async move {
+            c.create_and_send_transaction(
+                coin,
+                fee,
+                destination,
+                private_key,
+                chain_id,
+                account_number,
+                sequence,
+            )
+            .await
+        })
+    }
+}
+
+type BlockStream = Pin<Box<dyn Stream<Item = Result<Block, JsonRpcError>>>>;
+
+/// The iterator returned by ContactBlocking::blocks_from
+struct Blocks<'a> {
+    blocking: &'a ContactBlocking,
+    /// taken while a block is being fetched, the actix runtime requires 'static
+    /// futures so the stream is moved into the future rather than borrowed
+    stream: Option<BlockStream>,
+}
+
+impl Iterator for Blocks<'_> {
+    type Item = Result<Block, JsonRpcError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut stream = self.stream.take()?;
+        let (block, stream) = self.blocking.block_on(|_| async move {
+            let block = stream.next().await;
+            (block, stream)
+        });
+        self.stream = Some(stream);
+        block
+    }
+}
+
+/// The blocking version of AtHeight, created with ContactBlocking::at_height
+pub struct AtHeightBlocking<'a> {
+    blocking: &'a ContactBlocking,
+    height: u64,
+}
+
+impl AtHeightBlocking<'_> {
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    /// Runs the provided closure over an AtHeight for our height on the
+    /// runtime of the ContactBlocking we were created from
+    fn block_on<F, R>(&self, f: impl FnOnce(AtHeight) -> F) -> R
+    where
+        F: Future<Output = R> + 'static,
+    {
+        let height = self.height;
+        self.blocking.block_on(|c| f(c.at_height(height)))
+    }
+
+    pub fn get_block(&self) -> Result<Block, JsonRpcError> {
+        self.block_on(|c| async move { c.get_block().await })
+    }
+
+    pub fn get_validator_set(&self) -> Result<ResponseWrapper<ValidatorSet>, JsonRpcError> {
+        self.block_on(|c| async move { c.get_validator_set().await })
+    }
+
+    pub fn get_account_info(
+        &self,
+        address: Address,
+    ) -> Result<ResponseWrapper<TypeWrapper<Option<CosmosAccountInfo>>>, JsonRpcError> {
+        self.block_on(|c| async move { c.get_account_info(address).await })
+    }
+
+    pub fn get_account_number(&self, address: Address) -> Result<u64, JsonRpcError> {
+        self.block_on(|c| async move { c.get_account_number(address).await })
+    }
+
+    pub fn get_balances(
+        &self,
+        address: Address,
+    ) -> Result<ResponseWrapper<Vec<Coin>>, JsonRpcError> {
+        self.block_on(|c| async move { c.get_balances(address).await })
+    }
+
+    pub fn get_balances_many(
+        &self,
+        addresses: &[Address],
+        concurrency: usize,
+    ) -> HashMap<Address, Result<ResponseWrapper<Vec<Coin>>, JsonRpcError>> {
+        let addresses = addresses.to_vec();
+        self.block_on(|c| async move { c.get_balances_many(&addresses, concurrency).await })
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn get_account_info_many(
+        &self,
+        addresses: &[Address],
+        concurrency: usize,
+    ) -> HashMap<
+        Address,
+        Result<ResponseWrapper<TypeWrapper<Option<CosmosAccountInfo>>>, JsonRpcError>,
+    > {
+        let addresses = addresses.to_vec();
+        self.block_on(|c| async move { c.get_account_info_many(&addresses, concurrency).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonrpc::transport::mock::MockTransport;
+
+    #[test]
+    fn test_blocking_calls() {
+        let transport = MockTransport::default();
+        transport.respond("http://localhost/syncing", 200, r#"{"syncing":true}"#);
+        transport.respond(
+            "http://localhost/blocks/latest",
+            200,
+            r#"{"block_id":{"hash":"","parts":{"total":0,"hash":""}},"block":null}"#,
+        );
+        let contact =
+            ContactBlocking::with_transport("http://localhost", Duration::from_secs(1), transport);
+
+        assert!(contact.get_syncing_status().unwrap().syncing);
+        match contact.get_latest_block_number() {
+            Err(JsonRpcError::ChainNotRunning) => {}
+            res => panic!("Unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_blocking_views() {
+        let mut block: serde_json::Value = serde_json::from_str(include_str!(
+            "../../test_files/test_block_althea_testnet1v5.json"
+        ))
+        .unwrap();
+        let height = block["block"]["header"]["height"]
+            .as_str()
+            .unwrap()
+            .parse::<u64>()
+            .unwrap();
+        let transport = MockTransport::default();
+        transport.respond("http://localhost/blocks/latest", 200, &block.to_string());
+        block["block"]["header"]["height"] = serde_json::json!((height + 1).to_string());
+        transport.respond("http://localhost/blocks/latest", 200, &block.to_string());
+        let address = PrivateKey::from_secret(&[1; 32])
+            .to_public_key()
+            .unwrap()
+            .to_address();
+        transport.respond(
+            &format!("http://localhost/bank/balances/{}?height=7", address),
+            200,
+            r#"{"height":"7","result":[{"denom":"footoken","amount":"5"}]}"#,
+        );
+        let contact =
+            ContactBlocking::with_transport("http://localhost", Duration::from_secs(1), transport);
+
+        let at_height = contact.at_height(7);
+        assert_eq!(at_height.height(), 7);
+        let balances = at_height.get_balances(address).unwrap();
+        assert_eq!(balances.height, 7);
+        assert_eq!(balances.result[0].amount, 5u64.into());
+
+        let heights: Vec<u64> = contact
+            .blocks_from(height)
+            .take(2)
+            .map(|block| block.unwrap().height())
+            .collect();
+        assert_eq!(heights, vec![height, height + 1]);
+    }
+}
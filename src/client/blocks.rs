@@ -0,0 +1,241 @@
+use crate::client::wait::POLL_INTERVAL;
+use crate::client::Contact;
+use crate::jsonrpc::error::JsonRpcError;
+use crate::types::Block;
+use futures::stream::{self, Stream};
+use tokio::time::delay_for;
+
+impl Contact {
+    /// A stream of every block from `start_height` onwards, in order and without
+    /// gaps. Blocks already produced are fetched by height, after that the stream
+    /// follows the chain tip and yields each new block as it is produced.
+    ///
+    /// Errors that may go away, like a node being unreachable or a node we failed
+    /// over to being behind, are logged and the same height is tried again after
+    /// a short wait. Any other error, including the start height having been
+    /// pruned or a node that has passed a height not having a block for it, is
+    /// yielded and ends the stream.
+    pub fn blocks_from(
+        &self,
+        start_height: u64,
+    ) -> impl Stream<Item = Result<Block, JsonRpcError>> {
+        let follower = Follower {
+            contact: self.clone(),
+            next: start_height,
+            tip: None,
+            unavailable: None,
+            done: false,
+        };
+        stream::unfold(follower, |mut follower| async move {
+            if follower.done {
+                return None;
+            }
+            let res = follower.next_block().await;
+            follower.done = res.is_err();
+            Some((res, follower))
+        })
+    }
+}
+
+struct Follower {
+    contact: Contact,
+    /// the height of the next block to yield
+    next: u64,
+    /// the latest height the node has reported, if it has reported one since
+    /// we last had to start over
+    tip: Option<u64>,
+    /// set when the node said the next block is not available, which is only
+    /// returned if the node then reports a tip at or past that block
+    unavailable: Option<JsonRpcError>,
+    done: bool,
+}
+
+impl Follower {
+    async fn next_block(&mut self) -> Result<Block, JsonRpcError> {
+        loop {
+            match self.tip {
+                Some(tip) if self.next <= tip => match self.contact.get_block(self.next).await {
+                    Ok(block) => {
+                        self.next += 1;
+                        return Ok(block);
+                    }
+                    Err(JsonRpcError::HeightNotAvailable {
+                        lowest_available: Some(lowest),
+                        height,
+                    }) if height < lowest => {
+                        return Err(JsonRpcError::HeightNotAvailable {
+                            lowest_available: Some(lowest),
+                            height,
+                        })
+                    }
+                    Err(e @ JsonRpcError::HeightNotAvailable { .. }) => {
+                        // either we failed over to a node that is behind the last one, or
+                        // the block is missing for good, the node's tip tells us which
+                        info!("Block {} not available, checking the tip", self.next);
+                        self.unavailable = Some(e);
+                        self.tip = None;
+                        delay_for(POLL_INTERVAL).await;
+                    }
+                    Err(e) if is_transient(&e) => self.wait(e).await,
+                    Err(e) => return Err(e),
+                },
+                _ => match self.contact.get_latest_block().await {
+                    Ok(res) => match res.block {
+                        // saves fetching the block we were just sent again
+                        Some(block) if block.height() == self.next => {
+                            self.tip = Some(block.height());
+                            self.unavailable = None;
+                            self.next += 1;
+                            return Ok(block);
+                        }
+                        Some(block) if block.height() > self.next => {
+                            if let Some(e) = self.unavailable.take() {
+                                return Err(e);
+                            }
+                            self.tip = Some(block.height());
+                        }
+                        Some(block) => {
+                            trace!("Waiting for block {}, tip {}", self.next, block.height());
+                            self.tip = Some(block.height());
+                            self.unavailable = None;
+                            delay_for(POLL_INTERVAL).await;
+                        }
+                        None => delay_for(POLL_INTERVAL).await,
+                    },
+                    Err(e) if is_transient(&e) => self.wait(e).await,
+                    Err(e) => return Err(e),
+                },
+            }
+        }
+    }
+
+    async fn wait(&self, e: JsonRpcError) {
+        warn!("Failed to get block {}, trying again {}", self.next, e);
+        delay_for(POLL_INTERVAL).await;
+    }
+}
+
+fn is_transient(e: &JsonRpcError) -> bool {
+    e.is_retryable() || e.is_node_failure()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonrpc::error::TransportError;
+    use crate::jsonrpc::transport::mock::{test_contact, MockTransport};
+    use actix::System;
+    use futures::StreamExt;
+    use serde_json::Value;
+    use std::sync::Arc;
+
+    /// The test block with its height changed
+    fn block(height: u64) -> String {
+        let mut block: Value = serde_json::from_str(include_str!(
+            "../../test_files/test_block_althea_testnet1v5.json"
+        ))
+        .unwrap();
+        block["block"]["header"]["height"] = json!(height.to_string());
+        block["block"]["last_commit"]["height"] = json!((height - 1).to_string());
+        block.to_string()
+    }
+
+    #[test]
+    fn test_blocks_from() {
+        let transport = Arc::new(MockTransport::default());
+        transport.fail(
+            "http://a/blocks/latest",
+            TransportError::Connect("refused".to_string()),
+        );
+        transport.respond("http://a/blocks/latest", 200, &block(102));
+        transport.respond("http://a/blocks/latest", 200, &block(103));
+        transport.fail("http://a/blocks/100", TransportError::Timeout);
+        transport.respond("http://a/blocks/100", 200, &block(100));
+        transport.respond("http://a/blocks/101", 200, &block(101));
+        transport.respond("http://a/blocks/102", 200, &block(102));
+        transport.respond("http://a/blocks/103", 200, &block(103));
+        transport.respond(
+            "http://a/blocks/50",
+            200,
+            r#"{"block_id":{"hash":"","parts":{"total":0,"hash":""}},"block":null}"#,
+        );
+        transport.respond(
+            "http://a/blocks/5",
+            500,
+            r#"{"error":"height 5 is not available, lowest height is 100"}"#,
+        );
+        let contact = test_contact(&["http://a"], transport.clone());
+
+        System::new("test").block_on(async move {
+            let heights: Vec<u64> = contact
+                .blocks_from(100)
+                .take(4)
+                .map(|block| block.unwrap().height())
+                .collect()
+                .await;
+            assert_eq!(heights, vec![100, 101, 102, 103]);
+
+            // a pruned start height ends the stream
+            let res: Vec<_> = contact.blocks_from(5).collect().await;
+            assert_eq!(res.len(), 1);
+            assert!(matches!(
+                res[0],
+                Err(JsonRpcError::HeightNotAvailable { height: 5, .. })
+            ));
+
+            // so does a block missing below the tip, rather than waiting for it forever
+            let res: Vec<_> = contact.blocks_from(50).collect().await;
+            assert_eq!(res.len(), 1);
+            assert!(matches!(
+                res[0],
+                Err(JsonRpcError::HeightNotAvailable { height: 50, .. })
+            ));
+        });
+    }
+
+    #[test]
+    fn test_blocks_from_failover() {
+        let transport = Arc::new(MockTransport::default());
+        transport.respond("http://a/blocks/latest", 200, &block(105));
+        transport.fail(
+            "http://a/blocks/latest",
+            TransportError::Connect("refused".to_string()),
+        );
+        transport.respond("http://a/blocks/104", 200, &block(104));
+        transport.fail(
+            "http://a/blocks/105",
+            TransportError::Connect("refused".to_string()),
+        );
+        // b is a block behind a when a goes down
+        transport.respond("http://b/blocks/latest", 200, &block(104));
+        transport.respond("http://b/blocks/latest", 200, &block(106));
+        transport.respond(
+            "http://b/blocks/105",
+            400,
+            r#"{"error":"height 105 must be less than or equal to the current blockchain height 104"}"#,
+        );
+        transport.respond("http://b/blocks/105", 200, &block(105));
+        let contact = test_contact(&["http://a", "http://b"], transport.clone());
+
+        System::new("test").block_on(async move {
+            let heights: Vec<u64> = contact
+                .blocks_from(104)
+                .take(2)
+                .map(|block| block.unwrap().height())
+                .collect()
+                .await;
+            assert_eq!(heights, vec![104, 105]);
+        });
+        // b is asked for its tip rather than being treated as missing the block
+        let requests = transport.requests.lock().unwrap();
+        assert_eq!(
+            requests[requests.len() - 4..],
+            [
+                "http://b/blocks/105",
+                "http://b/blocks/latest",
+                "http://b/blocks/latest",
+                "http://b/blocks/105",
+            ]
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use deep_space::address::Address;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Settings for the optional response cache, see ContactBuilder::cache
+#[derive(Debug, Clone, Copy)]
+pub struct CacheConfig {
+    /// the maximum number of responses kept, the least recently used
+    /// response is dropped to make room for a new one
+    pub max_entries: usize,
+    /// how long results that change from block to block, like the latest
+    /// block or the syncing status, are kept
+    pub short_ttl: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            max_entries: 1000,
+            short_ttl: Duration::from_secs(1),
+        }
+    }
+}
+
+/// How long a response may be cached for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Lifetime {
+    /// data that never changes once it exists, like a tx by hash
+    Immutable,
+    /// data that changes with every block, kept for CacheConfig::short_ttl
+    Short,
+}
+
+/// Hit and miss counts for a ResponseCache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// the number of cached responses, not counting the chain id and
+    /// account numbers
+    pub entries: usize,
+}
+
+/// Caches query results for a Contact and the clones made from it. The chain
+/// id and account numbers never change and are kept for the life of the cache
+#[derive(Debug)]
+pub struct ResponseCache {
+    config: CacheConfig,
+    inner: Mutex<CacheInner>,
+}
+
+#[derive(Debug, Default)]
+struct CacheInner {
+    entries: HashMap<String, Entry>,
+    chain_id: Option<String>,
+    account_numbers: HashMap<Address, u64>,
+    /// incremented on every access, used to find the least recently used entry
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    value: Box<dyn Any + Send + Sync>,
+    expires: Option<Instant>,
+    last_used: u64,
+}
+
+impl Entry {
+    fn is_expired(&self, now: Instant) -> bool {
+        match self.expires {
+            Some(expires) => expires <= now,
+            None => false,
+        }
+    }
+}
+
+impl ResponseCache {
+    pub fn new(config: CacheConfig) -> Self {
+        ResponseCache {
+            config,
+            inner: Mutex::new(CacheInner::default()),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let inner = self.inner.lock().unwrap();
+        CacheStats {
+            hits: inner.hits,
+            misses: inner.misses,
+            entries: inner.entries.len(),
+        }
+    }
+
+    /// Drops every cached response, the chain id and account numbers are kept
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().entries.clear();
+    }
+
+    /// The cached response for `key` if there is one that has not expired
+    pub(crate) fn get<R: Clone + Send + Sync + 'static>(&self, key: &str) -> Option<R> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        let clock = inner.clock;
+        let now = Instant::now();
+        let hit = match inner.entries.get_mut(key) {
+            Some(entry) if !entry.is_expired(now) => {
+                entry.last_used = clock;
+                entry.value.downcast_ref::<R>().cloned()
+            }
+            _ => None,
+        };
+        match hit {
+            Some(_) => inner.hits += 1,
+            None => inner.misses += 1,
+        }
+        hit
+    }
+
+    pub(crate) fn insert<R: Clone + Send + Sync + 'static>(
+        &self,
+        key: &str,
+        value: &R,
+        lifetime: Lifetime,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        let now = Instant::now();
+        if !inner.entries.contains_key(key) && inner.entries.len() >= self.config.max_entries {
+            // expired entries go first, then the least recently used
+            let evict = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| (!e.is_expired(now), e.last_used))
+                .map(|(key, _)| key.clone());
+            if let Some(evict) = evict {
+                inner.entries.remove(&evict);
+            }
+        }
+        let entry = Entry {
+            value: Box::new(value.clone()),
+            expires: match lifetime {
+                Lifetime::Immutable => None,
+                Lifetime::Short => Some(now + self.config.short_ttl),
+            },
+            last_used: inner.clock,
+        };
+        if self.config.max_entries > 0 {
+            inner.entries.insert(key.to_string(), entry);
+        }
+    }
+
+    pub(crate) fn chain_id(&self) -> Option<String> {
+        let mut inner = self.inner.lock().unwrap();
+        let chain_id = inner.chain_id.clone();
+        match chain_id {
+            Some(_) => inner.hits += 1,
+            None => inner.misses += 1,
+        }
+        chain_id
+    }
+
+    pub(crate) fn set_chain_id(&self, chain_id: &str) {
+        self.inner.lock().unwrap().chain_id = Some(chain_id.to_string());
+    }
+
+    pub(crate) fn account_number(&self, address: Address) -> Option<u64> {
+        let mut inner = self.inner.lock().unwrap();
+        let account_number = inner.account_numbers.get(&address).cloned();
+        match account_number {
+            Some(_) => inner.hits += 1,
+            None => inner.misses += 1,
+        }
+        account_number
+    }
+
+    pub(crate) fn set_account_number(&self, address: Address, account_number: u64) {
+        self.inner
+            .lock()
+            .unwrap()
+            .account_numbers
+            .insert(address, account_number);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ContactBuilder;
+    use crate::jsonrpc::transport::mock::MockTransport;
+    use actix::System;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_chain_id_cached() {
+        let transport = Arc::new(MockTransport::default());
+        transport.respond(
+            "http://a/blocks/latest",
+            200,
+            include_str!("../../test_files/test_block_althea_testnet1v5.json"),
+        );
+        transport.respond(
+            "http://a/txs/ABCD",
+            200,
+            r#"{"height":"5","txhash":"ABCD"}"#,
+        );
+        let contact = ContactBuilder::new("http://a", Duration::from_secs(1))
+            .transport(transport.clone())
+            .cache(CacheConfig::default())
+            .build()
+            .unwrap();
+
+        let c = contact.clone();
+        System::new("test").block_on(async move {
+            let chain_id = c.get_chain_id().await.unwrap();
+            assert_eq!(c.get_chain_id().await.unwrap(), chain_id);
+            c.get_tx_by_hash("ABCD").await.unwrap();
+            c.get_tx_by_hash("ABCD").await.unwrap();
+        });
+        assert_eq!(
+            *transport.requests.lock().unwrap(),
+            vec!["http://a/blocks/latest", "http://a/txs/ABCD"]
+        );
+        let stats = contact.cache.unwrap().stats();
+        assert_eq!((stats.hits, stats.entries), (2, 2));
+    }
+
+    #[test]
+    fn test_lru_and_ttl() {
+        let cache = ResponseCache::new(CacheConfig {
+            max_entries: 2,
+            short_ttl: Duration::from_secs(0),
+        });
+        cache.insert("a", &1u64, Lifetime::Immutable);
+        cache.insert("b", &2u64, Lifetime::Immutable);
+        assert_eq!(cache.get::<u64>("a"), Some(1));
+        // b is now the least recently used
+        cache.insert("c", &3u64, Lifetime::Immutable);
+        assert_eq!(cache.get::<u64>("b"), None);
+        assert_eq!(cache.get::<u64>("c"), Some(3));
+
+        // short lived entries expire and are evicted first
+        cache.insert("a", &4u64, Lifetime::Short);
+        assert_eq!(cache.get::<u64>("a"), None);
+        cache.insert("d", &5u64, Lifetime::Immutable);
+        assert_eq!(cache.get::<u64>("c"), Some(3));
+        assert_eq!(cache.get::<u64>("d"), Some(5));
+
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 4,
+                misses: 2,
+                entries: 2
+            }
+        );
+    }
+}
@@ -0,0 +1,379 @@
+use crate::client::Contact;
+use crate::jsonrpc::client::decode_value;
+use crate::jsonrpc::error::JsonRpcError;
+use crate::types::{LatestBlockEndpointResponse, NodeInfoResponse};
+use serde_json::{Map, Value};
+
+/// The Cosmos SDK version that removed the legacy REST server
+const LEGACY_REST_REMOVED: SdkVersion = SdkVersion {
+    major: 0,
+    minor: 44,
+    patch: 0,
+};
+
+/// The first Cosmos SDK version to serve the gRPC gateway
+const GRPC_GATEWAY_ADDED: SdkVersion = SdkVersion {
+    major: 0,
+    minor: 40,
+    patch: 0,
+};
+
+/// The prefix of every gRPC gateway route
+pub(crate) const GATEWAY: &str = "cosmos/base/tendermint/v1beta1";
+
+/// A group of endpoints and the Cosmos SDK versions that serve them, None
+/// when there is no such version
+struct Route {
+    prefix: &'static str,
+    added: Option<SdkVersion>,
+    removed: Option<SdkVersion>,
+}
+
+impl Route {
+    const fn legacy(prefix: &'static str) -> Route {
+        Route {
+            prefix,
+            added: None,
+            removed: Some(LEGACY_REST_REMOVED),
+        }
+    }
+}
+
+/// The routes Contact requests, the first route a method starts with applies
+const ROUTES: &[Route] = &[
+    Route {
+        prefix: "cosmos/",
+        added: Some(GRPC_GATEWAY_ADDED),
+        removed: None,
+    },
+    Route::legacy("node_info"),
+    Route::legacy("syncing"),
+    Route::legacy("blocks"),
+    Route::legacy("validatorsets"),
+    Route::legacy("auth/accounts"),
+    Route::legacy("bank/balances"),
+    Route::legacy("txs"),
+];
+
+/// A Cosmos SDK release version, pre-release suffixes are ignored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SdkVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl SdkVersion {
+    /// Parses versions like `v0.40.0-rc3` or `0.42`, as reported in node_info
+    pub fn parse(version: &str) -> Option<SdkVersion> {
+        let version = version.trim().trim_start_matches('v');
+        // a char array pattern needs a newer compiler than we support
+        #[allow(clippy::manual_pattern_char_comparison)]
+        let release = version.split(|c| c == '-' || c == '+').next()?;
+        let mut parts = release.split('.').map(|p| p.parse::<u64>());
+        let major = parts.next()?.ok()?;
+        let minor = parts.next()?.ok()?;
+        let patch = match parts.next() {
+            Some(patch) => patch.ok()?,
+            None => 0,
+        };
+        Some(SdkVersion {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
+/// The API a node serves its responses from, each has its own routes and
+/// response shapes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    /// the legacy REST server, Cosmos SDK 0.43 and earlier. The response types
+    /// decode both the 0.39 and 0.40+ shapes it sends
+    Legacy,
+    /// the gRPC gateway, the only API Cosmos SDK 0.44 and later serve
+    Gateway,
+}
+
+/// What a node supports, detected from the versions it reports in node_info.
+/// Contact uses this to decode responses and to refuse requests to endpoints
+/// the node does not serve, see Contact::detect_capabilities
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// None for nodes older than Cosmos SDK 0.40, which don't report it
+    pub sdk_version: Option<SdkVersion>,
+    pub format: ResponseFormat,
+}
+
+impl Capabilities {
+    pub fn from_node_info(info: &NodeInfoResponse) -> Self {
+        let sdk_version = info
+            .application_version
+            .cosmos_sdk_version
+            .as_deref()
+            .and_then(SdkVersion::parse);
+        let format = match sdk_version {
+            Some(version) if version >= LEGACY_REST_REMOVED => ResponseFormat::Gateway,
+            _ => ResponseFormat::Legacy,
+        };
+        Capabilities {
+            sdk_version,
+            format,
+        }
+    }
+
+    /// Whether the node serves the provided method path, nodes that don't
+    /// report their version and methods we don't know the routes of are
+    /// assumed to be served
+    pub fn supports(&self, method: &str) -> bool {
+        let version = match self.sdk_version {
+            Some(version) => version,
+            None => return true,
+        };
+        match ROUTES.iter().find(|route| method.starts_with(route.prefix)) {
+            Some(route) => {
+                !matches!(route.added, Some(added) if version < added)
+                    && !matches!(route.removed, Some(removed) if version >= removed)
+            }
+            None => true,
+        }
+    }
+
+    /// Returns JsonRpcError::Unsupported if the node does not serve the method
+    pub(crate) fn check(&self, method: &str) -> Result<(), JsonRpcError> {
+        match self.sdk_version {
+            Some(version) if !self.supports(method) => Err(JsonRpcError::Unsupported {
+                method: method.to_string(),
+                sdk_version: format!("{}.{}.{}", version.major, version.minor, version.patch),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The header fields the gRPC gateway encodes in base64 rather than hex
+const GATEWAY_HEADER_HASHES: &[&str] = &[
+    "last_commit_hash",
+    "data_hash",
+    "validators_hash",
+    "next_validators_hash",
+    "consensus_hash",
+    "app_hash",
+    "last_results_hash",
+    "evidence_hash",
+    "proposer_address",
+];
+
+/// Decodes a block served by the gRPC gateway, which encodes hashes and addresses
+/// in base64 rather than hex, names block id parts part_set_header, and spells out
+/// signature flags, by converting it to the legacy shape
+pub(crate) fn decode_gateway_block(
+    mut value: Value,
+) -> Result<LatestBlockEndpointResponse, JsonRpcError> {
+    if let Some(res) = value.as_object_mut() {
+        gateway_block_id(res.get_mut("block_id"));
+        if let Some(block) = res.get_mut("block").and_then(Value::as_object_mut) {
+            if let Some(header) = block.get_mut("header").and_then(Value::as_object_mut) {
+                gateway_block_id(header.get_mut("last_block_id"));
+                for field in GATEWAY_HEADER_HASHES {
+                    base64_to_hex(header.get_mut(*field));
+                }
+            }
+            if let Some(evidence) = block.get_mut("evidence").and_then(Value::as_object_mut) {
+                // the legacy server sends each piece of evidence as a string
+                if let Some(Value::Array(list)) = evidence.get_mut("evidence") {
+                    for item in list.iter_mut().filter(|item| !item.is_string()) {
+                        *item = Value::String(item.to_string());
+                    }
+                }
+            }
+            if let Some(commit) = block.get_mut("last_commit").and_then(Value::as_object_mut) {
+                gateway_block_id(commit.get_mut("block_id"));
+                if let Some(Value::Array(signatures)) = commit.get_mut("signatures") {
+                    for signature in signatures.iter_mut().filter_map(Value::as_object_mut) {
+                        gateway_signature(signature);
+                    }
+                }
+            }
+        }
+    }
+    decode_value(value)
+}
+
+fn gateway_block_id(block_id: Option<&mut Value>) {
+    if let Some(block_id) = block_id.and_then(Value::as_object_mut) {
+        base64_to_hex(block_id.get_mut("hash"));
+        if let Some(mut parts) = block_id.remove("part_set_header") {
+            if let Some(parts) = parts.as_object_mut() {
+                base64_to_hex(parts.get_mut("hash"));
+            }
+            block_id.insert("parts".to_string(), parts);
+        }
+    }
+}
+
+fn gateway_signature(signature: &mut Map<String, Value>) {
+    base64_to_hex(signature.get_mut("validator_address"));
+    let flag = match signature.get("block_id_flag").and_then(Value::as_str) {
+        Some("BLOCK_ID_FLAG_ABSENT") => 1,
+        Some("BLOCK_ID_FLAG_COMMIT") => 2,
+        Some("BLOCK_ID_FLAG_NIL") => 3,
+        Some(_) => 0,
+        None => return,
+    };
+    signature.insert("block_id_flag".to_string(), flag.into());
+}
+
+/// Re-encodes a base64 string in upper case hex, anything else is left for the
+/// decoder to reject
+fn base64_to_hex(value: Option<&mut Value>) {
+    if let Some(value) = value {
+        let bytes = match value.as_str().map(base64::decode) {
+            Some(Ok(bytes)) => bytes,
+            _ => return,
+        };
+        *value = Value::String(bytes.iter().map(|b| format!("{:02X}", b)).collect());
+    }
+}
+
+impl Contact {
+    /// The format our node responds in, the legacy one until connect has
+    /// detected otherwise
+    pub(crate) fn response_format(&self) -> ResponseFormat {
+        match &self.capabilities {
+            Some(capabilities) => capabilities.format,
+            None => ResponseFormat::Legacy,
+        }
+    }
+
+    /// Queries node_info and works out what the node supports, connect does this
+    /// and stores the result in Contact::capabilities. See get_node_info for
+    /// nodes that no longer serve the legacy node_info endpoint
+    pub async fn detect_capabilities(&self) -> Result<Capabilities, JsonRpcError> {
+        Ok(Capabilities::from_node_info(&self.get_node_info().await?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ContactBuilder;
+    use crate::jsonrpc::transport::mock::MockTransport;
+    use actix::System;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn node_info(sdk_version: Option<&str>) -> NodeInfoResponse {
+        let mut info = NodeInfoResponse::default();
+        info.application_version.cosmos_sdk_version = sdk_version.map(|v| v.to_string());
+        info
+    }
+
+    #[test]
+    fn test_detect_capabilities() {
+        let launchpad = Capabilities::from_node_info(&node_info(None));
+        assert_eq!(launchpad.sdk_version, None);
+        assert_eq!(launchpad.format, ResponseFormat::Legacy);
+        assert!(launchpad.supports("auth/accounts/a"));
+
+        let stargate = Capabilities::from_node_info(&node_info(Some("v0.40.0-rc3")));
+        assert_eq!(
+            stargate.sdk_version,
+            Some(SdkVersion {
+                major: 0,
+                minor: 40,
+                patch: 0
+            })
+        );
+        assert_eq!(stargate.format, ResponseFormat::Legacy);
+        assert!(stargate.check("txs").is_ok());
+        assert!(stargate.supports("cosmos/base/tendermint/v1beta1/node_info"));
+        let old = Capabilities::from_node_info(&node_info(Some("v0.39.2")));
+        assert!(!old.supports("cosmos/base/tendermint/v1beta1/node_info"));
+
+        let removed = Capabilities::from_node_info(&node_info(Some("v0.45.1")));
+        match removed.check("txs") {
+            Err(JsonRpcError::Unsupported { sdk_version, .. }) => {
+                assert_eq!(sdk_version, "0.45.1")
+            }
+            res => panic!("Unexpected result {:?}", res),
+        }
+        assert_eq!(removed.format, ResponseFormat::Gateway);
+        assert!(!removed.supports("blocks/latest"));
+        assert!(removed.supports("cosmos/base/tendermint/v1beta1/node_info"));
+        // routes we don't know about are left for the node to answer
+        assert!(removed.supports("ibc/clients"));
+        assert_eq!(SdkVersion::parse("v0.44.0+dirty").unwrap().minor, 44);
+        assert_eq!(SdkVersion::parse("0.42").unwrap().minor, 42);
+        assert_eq!(SdkVersion::parse("latest"), None);
+    }
+
+    #[test]
+    fn test_decode_gateway_block() {
+        // the captured althea-testnet1v5 block and the same block re-encoded the
+        // way the gRPC gateway encodes blocks
+        let legacy: LatestBlockEndpointResponse = serde_json::from_str(include_str!(
+            "../../test_files/test_block_althea_testnet1v5.json"
+        ))
+        .unwrap();
+        let gateway = decode_gateway_block(
+            serde_json::from_str(include_str!("../../test_files/block_gateway.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(gateway.block_id.hash, legacy.block_id.hash);
+        assert_eq!(gateway.block_id.parts.hash, legacy.block_id.parts.hash);
+        let (gateway, legacy) = (gateway.block.unwrap(), legacy.block.unwrap());
+        assert_eq!(gateway.height(), legacy.height());
+        assert_eq!(gateway.header.app_hash, legacy.header.app_hash);
+        assert_eq!(
+            gateway.header.proposer_address,
+            legacy.header.proposer_address
+        );
+        assert_eq!(gateway.last_commit.signatures.len(), 58);
+        for (gateway, legacy) in gateway
+            .last_commit
+            .signatures
+            .iter()
+            .zip(legacy.last_commit.signatures.iter())
+        {
+            assert_eq!(gateway.block_id_flag, legacy.block_id_flag);
+            assert_eq!(gateway.validator_address, legacy.validator_address);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_requests_not_sent() {
+        let transport = Arc::new(MockTransport::default());
+        // nodes without the legacy REST server only serve node_info through the gateway
+        transport.respond(
+            "http://a/node_info",
+            501,
+            r#"{"code":12,"message":"Not Implemented","details":[]}"#,
+        );
+        transport.respond(
+            "http://a/cosmos/base/tendermint/v1beta1/node_info",
+            200,
+            r#"{"default_node_info":{"protocol_version":{"p2p":"8","block":"11","app":"0"},"default_node_id":"abcd","listen_addr":"tcp://0.0.0.0:26656","network":"testchain","version":"0.34.14","channels":"40202122233038606100","moniker":"node","other":{"tx_index":"on","rpc_address":"tcp://0.0.0.0:26657"}},"application_version":{"name":"gaia","app_name":"gaiad","version":"v6.0.0","git_commit":"abcd","build_tags":"netgo,ledger","go_version":"go version go1.17.2 linux/amd64","build_deps":[],"cosmos_sdk_version":"v0.44.3"}}"#,
+        );
+        let mut contact = ContactBuilder::new("http://a", Duration::from_secs(1))
+            .transport(transport.clone())
+            .build()
+            .unwrap();
+
+        System::new("test").block_on(async move {
+            let info = contact.get_node_info().await.unwrap();
+            assert_eq!(info.network(), "testchain");
+            assert_eq!(info.application_version.name, "gaia");
+            assert_eq!(info.node_info.id, "abcd");
+            contact.capabilities = Some(contact.detect_capabilities().await.unwrap());
+            assert!(matches!(
+                contact.get_validator_set(5).await,
+                Err(JsonRpcError::Unsupported { .. })
+            ));
+            // once the version is known the legacy endpoint isn't tried again
+            contact.get_node_info().await.unwrap();
+            assert_eq!(transport.requests.lock().unwrap().len(), 5);
+        });
+    }
+}
@@ -0,0 +1,141 @@
+use crate::client::{Capabilities, Contact, ContactBuilder};
+use crate::jsonrpc::error::JsonRpcError;
+use std::time::Duration;
+
+impl Contact {
+    /// Creates a Contact instance and checks that the node is usable before
+    /// returning it. Fails if the node can't be reached, is on a chain other
+    /// than `expected_chain_id`, is still syncing, or is waiting for the chain
+    /// to start. The returned Contact remembers the chain id and node version,
+    /// and what the node supports, see Capabilities.
+    pub async fn connect(
+        url: &str,
+        timeout: Duration,
+        expected_chain_id: Option<&str>,
+    ) -> Result<Contact, JsonRpcError> {
+        ContactBuilder::new(url, timeout)
+            .connect(expected_chain_id)
+            .await
+    }
+
+    pub(crate) async fn validate(
+        mut self,
+        expected_chain_id: Option<&str>,
+    ) -> Result<Contact, JsonRpcError> {
+        // all of the checks share our timeout
+        let mut contact = self.start_operation();
+        let node_info = contact.get_node_info().await?;
+        let capabilities = Capabilities::from_node_info(&node_info);
+        // so the checks below fail clearly on nodes that don't serve them
+        contact.capabilities = Some(capabilities);
+        let block = match contact.get_latest_block().await?.block {
+            Some(block) => block,
+            None => return Err(JsonRpcError::ChainNotRunning),
+        };
+        let chain_id = block.header.chain_id;
+        if let Some(expected) = expected_chain_id {
+            if expected != chain_id {
+                return Err(JsonRpcError::WrongChain {
+                    expected: expected.to_string(),
+                    actual: chain_id,
+                });
+            }
+        }
+        if contact.get_syncing_status().await?.syncing {
+            return Err(JsonRpcError::NodeSyncing);
+        }
+        self.chain_id = Some(chain_id);
+        self.node_version = Some(node_info.node_info.version);
+        self.capabilities = Some(capabilities);
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonrpc::transport::mock::MockTransport;
+    use actix::System;
+    use std::sync::Arc;
+
+    // althea-testnet1v5 runs Tendermint 0.34, block protocol 11, like the block below
+    const NODE_INFO: &str = r#"{"node_info":{"protocol_version":{"p2p":"8","block":"11","app":"0"},"id":"abcd","listen_addr":"tcp://0.0.0.0:26656","network":"althea-testnet1v5","version":"0.34.8","channels":"40202122233038606100","moniker":"validator","other":{"tx_index":"on","rpc_address":"tcp://0.0.0.0:26657"}}}"#;
+
+    const BLOCK: &str = include_str!("../../test_files/test_block_althea_testnet1v5.json");
+
+    fn connect(
+        block: &str,
+        syncing: &str,
+        expected_chain_id: Option<&'static str>,
+    ) -> Result<Contact, JsonRpcError> {
+        let transport = Arc::new(MockTransport::default());
+        transport.respond("http://a/node_info", 200, NODE_INFO);
+        transport.respond("http://a/blocks/latest", 200, block);
+        transport.respond("http://a/syncing", 200, syncing);
+        System::new("test").block_on(async move {
+            ContactBuilder::new("http://a", Duration::from_secs(1))
+                .transport(transport)
+                .retry_policy(crate::client::RetryPolicy::never())
+                .connect(expected_chain_id)
+                .await
+        })
+    }
+
+    #[test]
+    fn test_connect_gateway() {
+        // a Cosmos SDK 0.44 node only answers on the gRPC gateway
+        let transport = Arc::new(MockTransport::default());
+        let not_implemented = r#"{"code":12,"message":"Not Implemented","details":[]}"#;
+        transport.respond("http://a/node_info", 501, not_implemented);
+        let gateway = "http://a/cosmos/base/tendermint/v1beta1";
+        transport.respond(
+            &format!("{}/node_info", gateway),
+            200,
+            r#"{"default_node_info":{"protocol_version":{"p2p":"8","block":"11","app":"0"},"default_node_id":"abcd","listen_addr":"tcp://0.0.0.0:26656","network":"althea-testnet1v5","version":"0.34.13","channels":"40202122233038606100","moniker":"validator","other":{"tx_index":"on","rpc_address":"tcp://0.0.0.0:26657"}},"application_version":{"name":"althea","app_name":"althea","version":"v0.1.0","git_commit":"abcd","build_tags":"","go_version":"go version go1.16.8 linux/amd64","build_deps":[],"cosmos_sdk_version":"v0.44.2"}}"#,
+        );
+        transport.respond(
+            &format!("{}/blocks/latest", gateway),
+            200,
+            include_str!("../../test_files/block_gateway.json"),
+        );
+        transport.respond(&format!("{}/syncing", gateway), 200, r#"{"syncing":false}"#);
+        let contact = System::new("test")
+            .block_on(async move {
+                ContactBuilder::new("http://a", Duration::from_secs(1))
+                    .transport(transport)
+                    .retry_policy(crate::client::RetryPolicy::never())
+                    .connect(Some("althea-testnet1v5"))
+                    .await
+            })
+            .unwrap();
+        let capabilities = contact.capabilities.unwrap();
+        assert_eq!(capabilities.format, crate::client::ResponseFormat::Gateway);
+        assert_eq!(contact.node_version.as_deref(), Some("0.34.13"));
+    }
+
+    #[test]
+    fn test_connect() {
+        let synced = r#"{"syncing":false}"#;
+        let contact = connect(BLOCK, synced, None).unwrap();
+        let chain_id = contact.chain_id.clone().unwrap();
+        assert_eq!(contact.node_version.as_deref(), Some("0.34.8"));
+        assert_eq!(
+            contact.capabilities.unwrap().format,
+            crate::client::ResponseFormat::Legacy
+        );
+        assert!(connect(BLOCK, synced, Some("althea-testnet1v5")).is_ok());
+        match connect(BLOCK, synced, Some("some-other-chain")) {
+            Err(JsonRpcError::WrongChain { actual, .. }) => assert_eq!(actual, chain_id),
+            res => panic!("Unexpected result {:?}", res.map(|c| c.chain_id)),
+        }
+        assert!(matches!(
+            connect(BLOCK, r#"{"syncing":true}"#, None),
+            Err(JsonRpcError::NodeSyncing)
+        ));
+        let genesis = r#"{"block_id":{"hash":"","parts":{"total":0,"hash":""}},"block":null}"#;
+        assert!(matches!(
+            connect(genesis, synced, None),
+            Err(JsonRpcError::ChainNotRunning)
+        ));
+    }
+}
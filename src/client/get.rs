@@ -1,20 +1,26 @@
-use crate::client::Contact;
+use crate::client::cache::Lifetime;
+use crate::client::capabilities::{decode_gateway_block, GATEWAY};
+use crate::client::{Contact, ResponseFormat};
+use crate::jsonrpc::client::decode_value;
 use crate::jsonrpc::error::JsonRpcError;
+use crate::jsonrpc::quorum::{response_height, with_height};
 use crate::types::*;
 use deep_space::{address::Address, coin::Coin};
+use futures::future::join_all;
+use futures::stream::{self, StreamExt};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::future::Future;
 
 impl Contact {
+    /// The height of the latest block, the same height the wait_for helpers return
     pub async fn get_latest_block_number(&self) -> Result<u64, JsonRpcError> {
-        let none: Option<bool> = None;
-        let res: Result<LatestBlockEndpointResponse, JsonRpcError> = self
-            .jsonrpc_client
-            .request_method("blocks/latest", none, self.timeout, Some(5_000_000))
-            .await;
-
-        match res {
+        match self.get_latest_block().await {
             Ok(res) => {
                 if let Some(block) = res.block {
-                    Ok(block.last_commit.height)
+                    Ok(block.height())
                 } else {
                     Err(JsonRpcError::ChainNotRunning)
                 }
@@ -24,19 +30,169 @@ impl Contact {
     }
 
     pub async fn get_latest_block(&self) -> Result<LatestBlockEndpointResponse, JsonRpcError> {
+        let res: LatestBlockEndpointResponse = self
+            .cached(
+                "blocks/latest",
+                Lifetime::Short,
+                self.block_request("latest"),
+            )
+            .await?;
+        if let (Some(cache), Some(block)) = (&self.cache, &res.block) {
+            cache.set_chain_id(&block.header.chain_id);
+        }
+        Ok(res)
+    }
+
+    /// The block at the provided height, fails with JsonRpcError::HeightNotAvailable
+    /// if the node has pruned it or the chain has not reached it yet
+    pub async fn get_block(&self, height: u64) -> Result<Block, JsonRpcError> {
+        let method = format!("blocks/{}", height);
+        let res: LatestBlockEndpointResponse = self
+            .cached(
+                &method,
+                Lifetime::Immutable,
+                self.block_request(&height.to_string()),
+            )
+            .await
+            .map_err(|e| e.for_height(height))?;
+        res.block.ok_or(JsonRpcError::HeightNotAvailable {
+            height,
+            lowest_available: None,
+        })
+    }
+
+    /// The validator set at the provided height, fails with
+    /// JsonRpcError::HeightNotAvailable like get_block
+    pub async fn get_validator_set(
+        &self,
+        height: u64,
+    ) -> Result<ResponseWrapper<ValidatorSet>, JsonRpcError> {
         let none: Option<bool> = None;
-        self.jsonrpc_client
-            .request_method("blocks/latest", none, self.timeout, Some(5_000_000))
+        let method = format!("validatorsets/{}", height);
+        self.cached(&method, Lifetime::Immutable, self.request(&method, none))
             .await
+            .map_err(|e| e.for_height(height))
+    }
+
+    pub async fn get_latest_validator_set(
+        &self,
+    ) -> Result<ResponseWrapper<ValidatorSet>, JsonRpcError> {
+        let none: Option<bool> = None;
+        self.cached(
+            "validatorsets/latest",
+            Lifetime::Short,
+            self.request("validatorsets/latest", none),
+        )
+        .await
+    }
+
+    /// The id of the chain, this never changes so if we are connected or have
+    /// a cache it is only requested once
+    pub async fn get_chain_id(&self) -> Result<String, JsonRpcError> {
+        if let Some(chain_id) = &self.chain_id {
+            return Ok(chain_id.clone());
+        }
+        if let Some(chain_id) = self.cache.as_ref().and_then(|cache| cache.chain_id()) {
+            return Ok(chain_id);
+        }
+        match self.get_latest_block().await?.block {
+            Some(block) => Ok(block.header.chain_id),
+            None => Err(JsonRpcError::ChainNotRunning),
+        }
+    }
+
+    /// Information about the Tendermint node behind the LCD server. Cosmos SDK
+    /// 0.44 removed the legacy node_info endpoint, so for newer nodes this is
+    /// read from the gRPC gateway instead
+    pub async fn get_node_info(&self) -> Result<NodeInfoResponse, JsonRpcError> {
+        let none: Option<bool> = None;
+        let res = self
+            .cached(
+                "node_info",
+                Lifetime::Short,
+                self.request("node_info", none),
+            )
+            .await;
+        match res {
+            Err(JsonRpcError::HttpError {
+                status: 404 | 501, ..
+            })
+            | Err(JsonRpcError::Unsupported { .. }) => {
+                let method = format!("{}/node_info", GATEWAY);
+                self.cached(&method, Lifetime::Short, self.request(&method, none))
+                    .await
+            }
+            res => res,
+        }
     }
 
     pub async fn get_syncing_status(&self) -> Result<SyncingStatus, JsonRpcError> {
         let none: Option<bool> = None;
-        self.jsonrpc_client
-            .request_method("syncing", none, self.timeout, None)
+        let method = self.syncing_route();
+        self.cached(&method, Lifetime::Short, self.request(&method, none))
             .await
     }
 
+    /// The route our node serves its syncing status on, the response is the
+    /// same shape from either API
+    fn syncing_route(&self) -> String {
+        match self.response_format() {
+            ResponseFormat::Legacy => "syncing".to_string(),
+            ResponseFormat::Gateway => format!("{}/syncing", GATEWAY),
+        }
+    }
+
+    /// Requests the block at `height`, a number or latest, from the route and
+    /// with the decoder our node's response format needs
+    async fn block_request(
+        &self,
+        height: &str,
+    ) -> Result<LatestBlockEndpointResponse, JsonRpcError> {
+        let none: Option<bool> = None;
+        match self.response_format() {
+            ResponseFormat::Legacy => self.request(&format!("blocks/{}", height), none).await,
+            ResponseFormat::Gateway => {
+                let method = format!("{}/blocks/{}", GATEWAY, height);
+                decode_gateway_block(self.request(&method, none).await?)
+            }
+        }
+    }
+
+    /// Queries the syncing status of every node this Contact was created with, nodes
+    /// that report they are still syncing will not be used while a synced node is
+    /// available. Returns the number of nodes that responded and are not syncing.
+    /// Call this periodically to keep the syncing status up to date, or have it
+    /// done as needed with ContactBuilder::health_check_interval. No nodes are
+    /// checked once our deadline has passed.
+    pub async fn check_endpoints(&self) -> usize {
+        let timeout = match self.remaining_time() {
+            Ok(timeout) => timeout,
+            Err(_) => return 0,
+        };
+        let none: Option<bool> = None;
+        let method = self.syncing_route();
+        let endpoints = self.jsonrpc_client.endpoints().len();
+        let requests = (0..endpoints).map(|i| {
+            self.jsonrpc_client.request_method_on::<_, SyncingStatus>(
+                i,
+                &method,
+                none,
+                timeout,
+                Some(self.response_limits.default),
+            )
+        });
+        let mut synced = 0;
+        for (i, res) in join_all(requests).await.into_iter().enumerate() {
+            if let Ok(status) = res {
+                self.jsonrpc_client.set_syncing(i, status.syncing);
+                if !status.syncing {
+                    synced += 1;
+                }
+            }
+        }
+        synced
+    }
+
     /// Gets account info for the provided Cosmos account using the accounts endpoint
     /// accounts do not have any info if they have no tokens or are otherwise never seen
     /// before an Ok(None) result indicates this
@@ -44,26 +200,13 @@ impl Contact {
         &self,
         address: Address,
     ) -> Result<ResponseWrapper<TypeWrapper<Option<CosmosAccountInfo>>>, JsonRpcError> {
-        let none: Option<bool> = None;
-        let res = self
-            .jsonrpc_client
-            .request_method(
-                &format!("auth/accounts/{}", address),
-                none,
-                self.timeout,
-                None,
-            )
-            .await;
+        // the fallback request below has to fit in the same timeout
+        let contact = self.start_operation();
+        let res: Result<ResponseWrapper<TypeWrapper<Option<CosmosAccountInfo>>>, _> =
+            contact.query(&format!("auth/accounts/{}", address)).await;
         if let Err(JsonRpcError::BadStruct(_)) = res {
-            let res: Result<ResponseWrapper<TypeWrapper<Blank>>, JsonRpcError> = self
-                .jsonrpc_client
-                .request_method(
-                    &format!("auth/accounts/{}", address),
-                    none,
-                    self.timeout,
-                    None,
-                )
-                .await;
+            let res: Result<ResponseWrapper<TypeWrapper<Blank>>, JsonRpcError> =
+                contact.query(&format!("auth/accounts/{}", address)).await;
             let res = res?;
             Ok(ResponseWrapper {
                 height: res.height,
@@ -73,14 +216,35 @@ impl Contact {
                 },
             })
         } else {
+            if let (Some(cache), Ok(res)) = (&self.cache, &res) {
+                if let Some(info) = &res.result.value {
+                    cache.set_account_number(address, info.account_number);
+                }
+            }
             res
         }
     }
 
+    /// The account number of the provided account, this never changes so if we
+    /// have a cache it is only requested once
+    pub async fn get_account_number(&self, address: Address) -> Result<u64, JsonRpcError> {
+        if let Some(account_number) = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.account_number(address))
+        {
+            return Ok(account_number);
+        }
+        match self.get_account_info(address).await?.result.value {
+            Some(info) => Ok(info.account_number),
+            None => Err(JsonRpcError::NoToken),
+        }
+    }
+
     pub async fn get_tx_by_hash(&self, txhash: &str) -> Result<TXSendResponse, JsonRpcError> {
         let none: Option<bool> = None;
-        self.jsonrpc_client
-            .request_method(&format!("txs/{}", txhash), none, self.timeout, None)
+        let method = format!("txs/{}", txhash);
+        self.cached(&method, Lifetime::Immutable, self.request(&method, none))
             .await
     }
 
@@ -88,14 +252,233 @@ impl Contact {
         &self,
         address: Address,
     ) -> Result<ResponseWrapper<Vec<Coin>>, JsonRpcError> {
-        let none: Option<bool> = None;
-        self.jsonrpc_client
-            .request_method(
-                &format!("bank/balances/{}", address),
-                none,
-                self.timeout,
-                None,
-            )
+        self.query(&format!("bank/balances/{}", address)).await
+    }
+
+    /// Gets the balances of many accounts, with at most `concurrency` requests in
+    /// flight at a time. A failure for one address does not affect the others.
+    /// Each address is queried once, so an address listed twice has a single
+    /// entry in the result. Use at_height to read every balance from the same block.
+    pub async fn get_balances_many(
+        &self,
+        addresses: &[Address],
+        concurrency: usize,
+    ) -> HashMap<Address, Result<ResponseWrapper<Vec<Coin>>, JsonRpcError>> {
+        stream::iter(unique(addresses))
+            .map(|address| async move { (address, self.get_balances(address).await) })
+            .buffer_unordered(concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Gets the account info of many accounts, see get_balances_many
+    #[allow(clippy::type_complexity)]
+    pub async fn get_account_info_many(
+        &self,
+        addresses: &[Address],
+        concurrency: usize,
+    ) -> HashMap<
+        Address,
+        Result<ResponseWrapper<TypeWrapper<Option<CosmosAccountInfo>>>, JsonRpcError>,
+    > {
+        stream::iter(unique(addresses))
+            .map(|address| async move { (address, self.get_account_info(address).await) })
+            .buffer_unordered(concurrency.max(1))
+            .collect()
             .await
     }
+
+    /// A query of chain state, made at our pinned height if we have one
+    async fn query<R>(&self, method: &str) -> Result<R, JsonRpcError>
+    where
+        for<'de> R: Deserialize<'de>,
+        R: Debug + Clone + Send + Sync + 'static,
+    {
+        match self.height {
+            Some(height) => {
+                // state at a past height never changes
+                let method = with_height(method, height);
+                self.cached(&method, Lifetime::Immutable, async {
+                    let value: Value = self
+                        .quorum_get(&method)
+                        .await
+                        .map_err(|e| e.for_height(height))?;
+                    match response_height(&value) {
+                        Some(returned) if returned != height => Err(JsonRpcError::HeightMismatch {
+                            requested: height,
+                            returned,
+                        }),
+                        _ => decode_value(value),
+                    }
+                })
+                .await
+            }
+            None => self.quorum_get(method).await,
+        }
+    }
+
+    /// Runs the provided request unless our cache has a result for `key`,
+    /// successful results are added to the cache
+    async fn cached<R, F>(
+        &self,
+        key: &str,
+        lifetime: Lifetime,
+        request: F,
+    ) -> Result<R, JsonRpcError>
+    where
+        R: Clone + Send + Sync + 'static,
+        F: Future<Output = Result<R, JsonRpcError>>,
+    {
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return request.await,
+        };
+        if let Some(val) = cache.get(key) {
+            return Ok(val);
+        }
+        let res = request.await;
+        if let Ok(val) = &res {
+            cache.insert(key, val, lifetime);
+        }
+        res
+    }
+
+    /// A GET request that is made as a quorum read if this Contact has a quorum
+    /// configured and as a normal request otherwise, retried by our retry policy
+    async fn quorum_get<R>(&self, method: &str) -> Result<R, JsonRpcError>
+    where
+        for<'de> R: Deserialize<'de>,
+        R: Debug + 'static,
+    {
+        match self.quorum {
+            Some(quorum) => {
+                self.check_supported(method)?;
+                let limit = self.response_limits.for_method(method);
+                let mut attempts = 0;
+                let res = self
+                    .retry_policy
+                    .run(self.remaining_time()?, |timeout| {
+                        attempts += 1;
+                        self.jsonrpc_client.quorum_request_method(
+                            method,
+                            quorum,
+                            timeout,
+                            Some(limit),
+                        )
+                    })
+                    .await;
+                self.jsonrpc_client
+                    .metrics()
+                    .record_retries(method, attempts - 1);
+                self.check_deadline(res)
+            }
+            None => {
+                let none: Option<bool> = None;
+                self.request(method, none).await
+            }
+        }
+    }
+}
+
+/// The provided addresses without duplicates, in the order they first appear
+fn unique(addresses: &[Address]) -> Vec<Address> {
+    let mut seen = HashSet::new();
+    addresses
+        .iter()
+        .filter(|address| seen.insert(**address))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonrpc::error::TransportError;
+    use crate::jsonrpc::transport::mock::{test_contact, MockTransport};
+    use actix::System;
+    use deep_space::private_key::PrivateKey;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_get_block() {
+        let transport = Arc::new(MockTransport::default());
+        transport.respond(
+            "http://a/blocks/489421",
+            200,
+            include_str!("../../test_files/test_block_althea_testnet1v5.json"),
+        );
+        transport.respond(
+            "http://a/blocks/5",
+            500,
+            r#"{"error":"height 5 is not available, lowest height is 1000"}"#,
+        );
+        transport.respond(
+            "http://a/validatorsets/999999",
+            400,
+            r#"{"error":"height 999999 must be less than or equal to the current blockchain height 489421"}"#,
+        );
+        let contact = test_contact(&["http://a"], transport.clone());
+
+        System::new("test").block_on(async move {
+            assert_eq!(contact.get_block(489421).await.unwrap().height(), 489421);
+            match contact.get_block(5).await {
+                Err(JsonRpcError::HeightNotAvailable {
+                    height: 5,
+                    lowest_available: Some(1000),
+                }) => {}
+                res => panic!("Unexpected result {:?}", res),
+            }
+            match contact.get_validator_set(999999).await {
+                Err(JsonRpcError::HeightNotAvailable {
+                    lowest_available: None,
+                    ..
+                }) => {}
+                res => panic!("Unexpected result {:?}", res),
+            }
+        });
+    }
+
+    #[test]
+    fn test_bulk_queries() {
+        let addresses: Vec<Address> = (1..4u8)
+            .map(|i| {
+                PrivateKey::from_secret(&[i; 32])
+                    .to_public_key()
+                    .unwrap()
+                    .to_address()
+            })
+            .collect();
+        let transport = Arc::new(MockTransport::default());
+        for (i, address) in addresses.iter().enumerate().skip(1) {
+            transport.respond(
+                &format!("http://a/bank/balances/{}?height=7", address),
+                200,
+                &format!(
+                    r#"{{"height":"7","result":[{{"denom":"footoken","amount":"{}"}}]}}"#,
+                    i
+                ),
+            );
+        }
+        transport.fail(
+            &format!("http://a/bank/balances/{}?height=7", addresses[0]),
+            TransportError::Connect("refused".to_string()),
+        );
+        let contact = test_contact(&["http://a"], transport.clone());
+
+        // the repeated address is only queried once
+        let mut queried = addresses.clone();
+        queried.push(addresses[1]);
+        let res = System::new("test").block_on(async move {
+            contact
+                .at_height(7)
+                .get_balances_many(&queried, 2)
+                .await
+                .into_iter()
+                .map(|(address, res)| (address.to_string(), res))
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(res.len(), 3);
+        assert_eq!(res.iter().filter(|(_, res)| res.is_err()).count(), 1);
+        assert_eq!(transport.requests.lock().unwrap().len(), 3);
+    }
 }
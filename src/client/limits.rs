@@ -0,0 +1,82 @@
+use crate::client::capabilities::GATEWAY;
+
+/// The maximum response body size in bytes accepted for each class of LCD
+/// endpoint, responses larger than this fail with JsonRpcError::ResponseTooLarge.
+/// Responses are not decoded incrementally, a body is read into memory in full
+/// before it is decoded, so the limit also bounds the memory a response takes.
+/// Reading stops as soon as a body passes it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseLimits {
+    /// full blocks, `blocks/latest` and `blocks/{height}`
+    pub blocks: usize,
+    /// validator sets, `validatorsets/latest` and `validatorsets/{height}`
+    pub validator_sets: usize,
+    /// transaction lookups and searches, `txs/...`
+    pub txs: usize,
+    /// everything else
+    pub default: usize,
+}
+
+impl Default for ResponseLimits {
+    fn default() -> Self {
+        ResponseLimits {
+            blocks: 5_000_000,
+            validator_sets: 5_000_000,
+            txs: 5_000_000,
+            default: 1_000_000,
+        }
+    }
+}
+
+impl ResponseLimits {
+    /// The same limit for every endpoint
+    pub fn uniform(limit: usize) -> Self {
+        ResponseLimits {
+            blocks: limit,
+            validator_sets: limit,
+            txs: limit,
+            default: limit,
+        }
+    }
+
+    /// The limit for a request to the provided method path
+    pub fn for_method(&self, method: &str) -> usize {
+        // gateway routes are limited like the legacy routes they replace
+        let method = method.trim_start_matches(GATEWAY).trim_start_matches('/');
+        if method.starts_with("blocks") {
+            self.blocks
+        } else if method.starts_with("validatorsets") {
+            self.validator_sets
+        } else if method.starts_with("txs") {
+            self.txs
+        } else {
+            self.default
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_classes() {
+        let limits = ResponseLimits {
+            blocks: 3,
+            validator_sets: 4,
+            txs: 2,
+            default: 1,
+        };
+        assert_eq!(limits.for_method("blocks/latest"), 3);
+        assert_eq!(limits.for_method("blocks/100"), 3);
+        assert_eq!(limits.for_method("validatorsets/latest"), 4);
+        assert_eq!(limits.for_method("validatorsets/100"), 4);
+        assert_eq!(limits.for_method("txs?message.action=send"), 2);
+        assert_eq!(limits.for_method("txs/ABCD"), 2);
+        assert_eq!(limits.for_method("syncing"), 1);
+        assert_eq!(
+            limits.for_method("cosmos/base/tendermint/v1beta1/blocks/latest"),
+            3
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use crate::client::CosmosClient;
+use crate::jsonrpc::error::JsonRpcError;
+use crate::types::*;
+use async_trait::async_trait;
+use deep_space::address::Address;
+use deep_space::coin::Coin;
+use deep_space::msg::Msg;
+use deep_space::private_key::PrivateKey;
+use deep_space::transaction::Transaction;
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// A scripted response, the value is downcast to the type the method returns
+type Scripted = Result<Arc<dyn Any + Send + Sync>, JsonRpcError>;
+
+/// A call made to a MockContact, `argument` is the address, hash, height or
+/// json encoded transaction the method was called with, if it takes one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockCall {
+    pub method: String,
+    pub argument: Option<String>,
+}
+
+/// An in memory CosmosClient for unit tests. Responses are scripted per
+/// method, optionally for a specific argument, and every call is recorded.
+///
+/// ```
+/// # use contact::client::{CosmosClient, MockContact};
+/// # use contact::types::SyncingStatus;
+/// let mock = MockContact::default();
+/// mock.respond("get_syncing_status", SyncingStatus { syncing: false });
+/// # actix::System::new("doc").block_on(async move {
+/// assert!(!mock.get_syncing_status().await.unwrap().syncing);
+/// assert_eq!(mock.calls()[0].method, "get_syncing_status");
+/// # });
+/// ```
+///
+/// Responses are returned in the order they were added and the last successful
+/// response for a method is repeated once the rest are used up, scripted errors
+/// are only returned once. Calls without a scripted response, or with a response
+/// of the wrong type, fail with BadInput.
+#[derive(Default)]
+pub struct MockContact {
+    responses: Mutex<HashMap<String, VecDeque<Scripted>>>,
+    calls: Mutex<Vec<MockCall>>,
+}
+
+impl MockContact {
+    /// Scripts a response for every call to `method`, the name of a CosmosClient method
+    pub fn respond<T: Send + Sync + 'static>(&self, method: &str, response: T) {
+        self.push(method.to_string(), Ok(Arc::new(response)))
+    }
+
+    /// Scripts a response for calls to `method` with a specific argument, these
+    /// take precedence over responses for every call
+    pub fn respond_for<T: Send + Sync + 'static>(&self, method: &str, argument: &str, response: T) {
+        self.push(key(method, argument), Ok(Arc::new(response)))
+    }
+
+    /// Scripts an error for the next call to `method`
+    pub fn fail(&self, method: &str, error: JsonRpcError) {
+        self.push(method.to_string(), Err(error))
+    }
+
+    /// Scripts an error for the next call to `method` with a specific argument
+    pub fn fail_for(&self, method: &str, argument: &str, error: JsonRpcError) {
+        self.push(key(method, argument), Err(error))
+    }
+
+    /// Every call made so far, oldest first
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn push(&self, key: String, res: Scripted) {
+        self.responses
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_default()
+            .push_back(res)
+    }
+
+    fn call<R: Clone + 'static>(
+        &self,
+        method: &str,
+        argument: Option<String>,
+    ) -> Result<R, JsonRpcError> {
+        self.calls.lock().unwrap().push(MockCall {
+            method: method.to_string(),
+            argument: argument.clone(),
+        });
+        let mut responses = self.responses.lock().unwrap();
+        let specific = argument.map(|argument| key(method, &argument));
+        // a specific queue is emptied once its scripted errors are used up, after
+        // that calls fall back to the responses for every call
+        let queue = match specific.filter(|k| matches!(responses.get(k), Some(q) if !q.is_empty()))
+        {
+            Some(k) => responses.get_mut(&k),
+            None => responses.get_mut(method),
+        };
+        let res = match queue {
+            Some(queue) if queue.len() > 1 => queue.pop_front().unwrap(),
+            Some(queue) => match queue.front() {
+                Some(Ok(value)) => Ok(value.clone()),
+                Some(Err(_)) => queue.pop_front().unwrap(),
+                None => Err(no_response(method)),
+            },
+            None => Err(no_response(method)),
+        };
+        match res?.downcast_ref::<R>() {
+            Some(value) => Ok(value.clone()),
+            None => Err(JsonRpcError::BadInput(format!(
+                "Mock response for {} has the wrong type",
+                method
+            ))),
+        }
+    }
+}
+
+fn key(method: &str, argument: &str) -> String {
+    format!("{}({})", method, argument)
+}
+
+fn no_response(method: &str) -> JsonRpcError {
+    JsonRpcError::BadInput(format!("No mock response for {}", method))
+}
+
+#[cfg_attr(feature = "actix", async_trait(?Send))]
+#[cfg_attr(not(feature = "actix"), async_trait)]
+impl CosmosClient for MockContact {
+    async fn get_latest_block_number(&self) -> Result<u64, JsonRpcError> {
+        self.call("get_latest_block_number", None)
+    }
+
+    async fn get_latest_block(&self) -> Result<LatestBlockEndpointResponse, JsonRpcError> {
+        self.call("get_latest_block", None)
+    }
+
+    async fn get_block(&self, height: u64) -> Result<Block, JsonRpcError> {
+        self.call("get_block", Some(height.to_string()))
+    }
+
+    async fn get_validator_set(
+        &self,
+        height: u64,
+    ) -> Result<ResponseWrapper<ValidatorSet>, JsonRpcError> {
+        self.call("get_validator_set", Some(height.to_string()))
+    }
+
+    async fn get_chain_id(&self) -> Result<String, JsonRpcError> {
+        self.call("get_chain_id", None)
+    }
+
+    async fn get_node_info(&self) -> Result<NodeInfoResponse, JsonRpcError> {
+        self.call("get_node_info", None)
+    }
+
+    async fn get_syncing_status(&self) -> Result<SyncingStatus, JsonRpcError> {
+        self.call("get_syncing_status", None)
+    }
+
+    async fn get_account_info(
+        &self,
+        address: Address,
+    ) -> Result<ResponseWrapper<TypeWrapper<Option<CosmosAccountInfo>>>, JsonRpcError> {
+        self.call("get_account_info", Some(address.to_string()))
+    }
+
+    async fn get_account_number(&self, address: Address) -> Result<u64, JsonRpcError> {
+        self.call("get_account_number", Some(address.to_string()))
+    }
+
+    async fn get_tx_by_hash(&self, txhash: &str) -> Result<TXSendResponse, JsonRpcError> {
+        self.call("get_tx_by_hash", Some(txhash.to_string()))
+    }
+
+    async fn get_balances(
+        &self,
+        address: Address,
+    ) -> Result<ResponseWrapper<Vec<Coin>>, JsonRpcError> {
+        self.call("get_balances", Some(address.to_string()))
+    }
+
+    async fn send_transaction(&self, tx: Transaction<Msg>) -> Result<TXSendResponse, JsonRpcError> {
+        let tx = serde_json::to_string(&tx).map_err(|e| JsonRpcError::BadInput(e.to_string()))?;
+        self.call("send_transaction", Some(tx))
+    }
+
+    async fn create_and_send_transaction(
+        &self,
+        coin: Coin,
+        _fee: Coin,
+        destination: Address,
+        _private_key: PrivateKey,
+        _chain_id: Option<String>,
+        _account_number: Option<u64>,
+        _sequence: Option<u64>,
+    ) -> Result<TXSendResponse, JsonRpcError> {
+        let argument = format!("{}{} to {}", coin.amount, coin.denom, destination);
+        self.call("create_and_send_transaction", Some(argument))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix::System;
+
+    /// Stands in for downstream code that only depends on the trait
+    async fn total_balance<C: CosmosClient>(client: &C, addresses: &[Address]) -> u64 {
+        let mut total = 0u64;
+        for address in addresses {
+            if let Ok(balances) = client.get_balances(*address).await {
+                for coin in balances.result {
+                    total += coin.amount.to_string().parse::<u64>().unwrap();
+                }
+            }
+        }
+        total
+    }
+
+    #[test]
+    fn test_mock_contact() {
+        let a = PrivateKey::from_secret(&[1; 32])
+            .to_public_key()
+            .unwrap()
+            .to_address();
+        let b = PrivateKey::from_secret(&[2; 32])
+            .to_public_key()
+            .unwrap()
+            .to_address();
+        let mock = MockContact::default();
+        let coin = |amount: u32| Coin {
+            denom: "footoken".to_string(),
+            amount: amount.into(),
+        };
+        mock.respond(
+            "get_balances",
+            ResponseWrapper {
+                height: 1,
+                result: vec![coin(5)],
+            },
+        );
+        mock.respond_for(
+            "get_balances",
+            &b.to_string(),
+            ResponseWrapper {
+                height: 1,
+                result: vec![coin(7)],
+            },
+        );
+        mock.fail("get_chain_id", JsonRpcError::ChainNotRunning);
+
+        System::new("test").block_on(async move {
+            assert_eq!(total_balance(&mock, &[a, b, a]).await, 17);
+            match mock.get_chain_id().await {
+                Err(JsonRpcError::ChainNotRunning) => {}
+                res => panic!("Unexpected result {:?}", res),
+            }
+            // the scripted error is used up
+            assert!(matches!(
+                mock.get_chain_id().await,
+                Err(JsonRpcError::BadInput(_))
+            ));
+            let calls = mock.calls();
+            assert_eq!(calls.len(), 5);
+            assert_eq!(calls[1].argument, Some(b.to_string()));
+
+            // once a scripted error for an argument is used up, calls with that
+            // argument get the response for every call
+            mock.fail_for("get_balances", &a.to_string(), JsonRpcError::NodeSyncing);
+            assert!(matches!(
+                mock.get_balances(a).await,
+                Err(JsonRpcError::NodeSyncing)
+            ));
+            assert_eq!(total_balance(&mock, &[a]).await, 5);
+        });
+    }
+}
@@ -1,40 +1,376 @@
 use crate::jsonrpc::client::HTTPClient;
+use crate::jsonrpc::error::{JsonRpcError, TransportError};
+use crate::jsonrpc::quorum::QuorumConfig;
+use crate::jsonrpc::transport::Transport;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+mod api;
+mod at_height;
+mod blocking;
+mod blocks;
+mod builder;
+mod cache;
+mod capabilities;
+mod connect;
 mod get;
+mod limits;
+mod mock;
+mod retry;
 mod send;
+mod wait;
+
+pub use api::CosmosClient;
+pub use at_height::AtHeight;
+pub use blocking::{AtHeightBlocking, ContactBlocking};
+pub use builder::ContactBuilder;
+pub use cache::{CacheConfig, CacheStats, ResponseCache};
+pub use capabilities::{Capabilities, ResponseFormat, SdkVersion};
+pub use limits::ResponseLimits;
+pub use mock::{MockCall, MockContact};
+pub use retry::RetryPolicy;
 
 /// An instance of Contact Cosmos RPC Client.
 #[derive(Clone)]
 pub struct Contact {
-    pub jsonrpc_client: Arc<Box<HTTPClient>>,
+    pub jsonrpc_client: Arc<HTTPClient>,
     pub timeout: Duration,
+    /// When set balances and account info are read from several nodes and
+    /// only returned if enough of them agree, see QuorumConfig
+    pub quorum: Option<QuorumConfig>,
+    /// How failed requests are retried, this applies to every query and send
+    pub retry_policy: RetryPolicy,
+    /// When set every request made by this instance must finish before this
+    /// point in time. Composite operations set this so that all of their
+    /// requests share a single timeout, see with_deadline
+    pub deadline: Option<Instant>,
+    /// The largest response accepted from each class of endpoint
+    pub response_limits: ResponseLimits,
+    /// When set account and balance queries return the state at this block
+    /// height rather than the latest state, see at_height
+    pub height: Option<u64>,
+    /// When set query results are cached, see ContactBuilder::cache
+    pub cache: Option<Arc<ResponseCache>>,
+    /// The chain id of the node, known once connect has checked it
+    pub chain_id: Option<String>,
+    /// The Tendermint version of the node, known once connect has checked it
+    pub node_version: Option<String>,
+    /// What the node supports, requests to endpoints it does not serve fail
+    /// with JsonRpcError::Unsupported without being sent. Set by connect
+    pub capabilities: Option<Capabilities>,
 }
 
 impl Contact {
+    /// Creates a Contact instance with the default settings, see ContactBuilder
+    /// for authentication, TLS and proxy options. This does not contact the node,
+    /// use connect to check that the node is usable first. Panics if the default
+    /// transport can't be created, ContactBuilder returns an error instead
     pub fn new(url: &str, timeout: Duration) -> Self {
-        let mut url = url;
-        if !url.ends_with('/') {
-            url = url.trim_end_matches('/');
+        Self::from_urls(&[url], timeout)
+    }
+
+    /// Creates a Contact instance that sends all of its requests using the
+    /// provided Transport rather than the default transport
+    pub fn with_transport<T: Transport + 'static>(
+        url: &str,
+        timeout: Duration,
+        transport: T,
+    ) -> Self {
+        Self::with_endpoints(&[url], timeout, transport)
+    }
+
+    /// Creates a Contact instance that spreads its requests across several LCD
+    /// nodes, each request goes to the healthiest node and fails over to the
+    /// next healthiest if that node is unreachable or returns a bad response.
+    /// Panics if `urls` is empty or the default transport can't be created,
+    /// ContactBuilder returns an error instead
+    pub fn from_urls(urls: &[&str], timeout: Duration) -> Self {
+        ContactBuilder::with_urls(urls, timeout)
+            .build()
+            .expect("Default Contact configuration is invalid!")
+    }
+
+    /// Same as from_urls but using the provided Transport, panics if `urls`
+    /// is empty
+    pub fn with_endpoints<T: Transport + 'static>(
+        urls: &[&str],
+        timeout: Duration,
+        transport: T,
+    ) -> Self {
+        ContactBuilder::with_urls(urls, timeout)
+            .transport(transport)
+            .build()
+            .expect("Default Contact configuration is invalid!")
+    }
+
+    /// Returns a copy of this Contact where every request, including retries, must
+    /// complete before the provided deadline. Each request gets whatever time remains
+    /// up to the normal timeout, once the deadline passes requests fail with
+    /// JsonRpcError::DeadlineExceeded. An earlier existing deadline is kept.
+    pub fn with_deadline(&self, deadline: Instant) -> Contact {
+        let mut contact = self.clone();
+        contact.deadline = Some(match self.deadline {
+            Some(existing) => existing.min(deadline),
+            None => deadline,
+        });
+        contact
+    }
+
+    /// A view of the chain state at the provided block height, every query made
+    /// through it returns the state at that height or fails with
+    /// JsonRpcError::HeightMismatch, so several reads are guaranteed to agree
+    /// with each other. Nodes only keep old state until it is pruned.
+    pub fn at_height(&self, height: u64) -> AtHeight {
+        let mut contact = self.clone();
+        contact.height = Some(height);
+        AtHeight::new(contact)
+    }
+
+    /// A copy of this contact for an operation that makes several requests, all of
+    /// which have to fit within a single timeout
+    pub(crate) fn start_operation(&self) -> Contact {
+        self.with_deadline(Instant::now() + self.timeout)
+    }
+
+    /// The time budget for the next request, our timeout or whatever is left
+    /// before the deadline if that is shorter
+    pub(crate) fn remaining_time(&self) -> Result<Duration, JsonRpcError> {
+        match self.deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    Err(JsonRpcError::DeadlineExceeded)
+                } else {
+                    Ok((deadline - now).min(self.timeout))
+                }
+            }
+            None => Ok(self.timeout),
         }
-        Self {
-            jsonrpc_client: Arc::new(Box::new(HTTPClient::new(&url))),
-            timeout,
+    }
+
+    /// Fails with JsonRpcError::Unsupported if we know the node does not serve
+    /// the provided method
+    pub(crate) fn check_supported(&self, method: &str) -> Result<(), JsonRpcError> {
+        match &self.capabilities {
+            Some(capabilities) => capabilities.check(method),
+            None => Ok(()),
+        }
+    }
+
+    /// Converts a request timeout into a deadline error if the deadline is to blame
+    pub(crate) fn check_deadline<R>(
+        &self,
+        res: Result<R, JsonRpcError>,
+    ) -> Result<R, JsonRpcError> {
+        match (res, self.deadline) {
+            (Err(JsonRpcError::FailedToSend(TransportError::Timeout)), Some(deadline))
+                if Instant::now() >= deadline =>
+            {
+                Err(JsonRpcError::DeadlineExceeded)
+            }
+            (res, _) => res,
         }
     }
+
+    /// Makes a request with our timeout and response limits, retrying it
+    /// according to our retry policy
+    pub(crate) async fn request<T, R>(
+        &self,
+        method: &str,
+        params: Option<T>,
+    ) -> Result<R, JsonRpcError>
+    where
+        T: Serialize + Clone,
+        for<'de> R: Deserialize<'de>,
+        R: Debug + 'static,
+    {
+        self.check_supported(method)?;
+        let limit = self.response_limits.for_method(method);
+        let mut attempts = 0;
+        let res = self
+            .retry_policy
+            .run(self.remaining_time()?, |timeout| {
+                attempts += 1;
+                self.jsonrpc_client
+                    .request_method(method, params.clone(), timeout, Some(limit))
+            })
+            .await;
+        self.jsonrpc_client
+            .metrics()
+            .record_retries(method, attempts - 1);
+        self.check_deadline(res)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::jsonrpc::error::JsonRpcError;
+    use crate::jsonrpc::transport::mock::{test_contact, MockTransport};
     use actix::Arbiter;
     use actix::System;
     use deep_space::address::Address;
     use deep_space::coin::Coin;
     use deep_space::private_key::PrivateKey;
     use rand::Rng;
+    use tokio::time::delay_for;
+
+    #[test]
+    fn test_failover() {
+        let transport = Arc::new(MockTransport::default());
+        transport.fail(
+            "http://a/syncing",
+            TransportError::Connect("refused".to_string()),
+        );
+        transport.respond("http://b/syncing", 200, r#"{"syncing":true}"#);
+        transport.respond("http://c/syncing", 200, r#"{"syncing":false}"#);
+        let contact = Contact::with_endpoints(
+            &["http://a", "http://b", "http://c"],
+            Duration::from_secs(1),
+            transport.clone(),
+        );
+
+        System::new("test").block_on(async move {
+            // a is down so we should fail over to b
+            assert!(contact.get_syncing_status().await.unwrap().syncing);
+            assert_eq!(
+                *transport.requests.lock().unwrap(),
+                vec!["http://a/syncing", "http://b/syncing"]
+            );
+
+            // b is syncing and a is down, leaving c as the only healthy node
+            assert_eq!(contact.check_endpoints().await, 1);
+            transport.requests.lock().unwrap().clear();
+            assert!(!contact.get_syncing_status().await.unwrap().syncing);
+            assert_eq!(
+                *transport.requests.lock().unwrap(),
+                vec!["http://c/syncing"]
+            );
+
+            let health = contact.jsonrpc_client.endpoints();
+            assert_eq!(health[0].1.failures, 2);
+            assert!(health[1].1.syncing);
+            assert_eq!(health[2].1.successes, 2);
+
+            let metrics = contact.jsonrpc_client.metrics();
+            assert_eq!(metrics.requests("http://a"), 2);
+            assert!(metrics
+                .to_prometheus()
+                .contains("contact_errors_total{endpoint=\"http://a\",error=\"FailedToSend\"} 2"));
+        });
+    }
+
+    #[test]
+    fn test_hung_endpoint() {
+        let transport = Arc::new(MockTransport::default());
+        transport.fail("http://a/syncing", TransportError::Timeout);
+        transport.respond("http://b/syncing", 200, r#"{"syncing":false}"#);
+        let contact = test_contact(&["http://a", "http://b"], transport.clone());
+
+        System::new("test").block_on(async move {
+            // a only gets its share of the timeout, leaving time to fail over to b
+            let start = Instant::now();
+            assert!(!contact.get_syncing_status().await.unwrap().syncing);
+            let elapsed = Instant::now() - start;
+            assert!(elapsed >= Duration::from_millis(500) && elapsed < Duration::from_secs(1));
+        });
+    }
+
+    #[test]
+    fn test_health_check_interval() {
+        let transport = Arc::new(MockTransport::default());
+        transport.respond("http://a/syncing", 200, r#"{"syncing":true}"#);
+        transport.respond("http://a/syncing", 200, r#"{"syncing":false}"#);
+        transport.respond("http://b/syncing", 200, r#"{"syncing":false}"#);
+        let block = include_str!("../../test_files/test_block_althea_testnet1v5.json");
+        transport.respond("http://a/blocks/latest", 200, block);
+        transport.respond("http://b/blocks/latest", 200, block);
+        let contact = ContactBuilder::new("http://a", Duration::from_secs(1))
+            .endpoint("http://b")
+            .transport(transport.clone())
+            .health_check_interval(Duration::from_millis(100))
+            .build()
+            .unwrap();
+
+        System::new("test").block_on(async move {
+            // a is found to be syncing before it is used
+            contact.get_latest_block().await.unwrap();
+            assert!(contact.jsonrpc_client.endpoints()[0].1.syncing);
+            contact.get_latest_block().await.unwrap();
+            // and is used again once it has caught up
+            delay_for(Duration::from_millis(100)).await;
+            contact.get_latest_block().await.unwrap();
+            assert_eq!(
+                *transport.requests.lock().unwrap(),
+                vec![
+                    "http://a/syncing",
+                    "http://b/syncing",
+                    "http://b/blocks/latest",
+                    "http://b/blocks/latest",
+                    "http://a/syncing",
+                    "http://b/syncing",
+                    "http://a/blocks/latest",
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn test_deadline() {
+        let transport = Arc::new(MockTransport::default());
+        transport.respond("http://a/syncing", 200, r#"{"syncing":false}"#);
+        let contact =
+            Contact::with_transport("http://a", Duration::from_secs(30), transport.clone());
+
+        let soon = contact.with_deadline(Instant::now() + Duration::from_secs(5));
+        assert!(soon.remaining_time().unwrap() <= Duration::from_secs(5));
+        // the earlier of two deadlines wins
+        let later = soon.with_deadline(Instant::now() + Duration::from_secs(60));
+        assert_eq!(later.deadline, soon.deadline);
+
+        let passed = contact.with_deadline(Instant::now());
+        System::new("test").block_on(async move {
+            assert!(contact.get_syncing_status().await.is_ok());
+            match passed.get_syncing_status().await {
+                Err(JsonRpcError::DeadlineExceeded) => {}
+                res => panic!("Unexpected result {:?}", res),
+            }
+            // no request should have been made once the deadline passed
+            assert_eq!(transport.requests.lock().unwrap().len(), 1);
+        });
+    }
+
+    /// Without the actix transport everything Contact returns can be spawned
+    /// onto a multi threaded runtime
+    #[cfg(not(feature = "actix"))]
+    #[test]
+    fn test_futures_are_send() {
+        fn assert_send<T: Send>(_: &T) {}
+        let key = PrivateKey::from_secret(&[1; 32]);
+        let address = key.to_public_key().unwrap().to_address();
+        let coin = Coin {
+            denom: "footoken".to_string(),
+            amount: 1u32.into(),
+        };
+        let contact =
+            Contact::with_transport("http://a", Duration::from_secs(1), MockTransport::default());
+
+        assert_send(&contact);
+        assert_send(&contact.get_balances(address));
+        assert_send(&contact.wait_for_sync(Duration::from_secs(1)));
+        assert_send(&contact.blocks_from(1));
+        assert_send(&contact.create_and_send_transaction(
+            coin.clone(),
+            coin,
+            address,
+            key,
+            None,
+            None,
+            None,
+        ));
+        assert_send(&CosmosClient::get_latest_block(&contact));
+    }
 
     /// If you run the start-chains.sh script in the peggy repo it will pass
     /// port 1317 on localhost through to the peggycli rest-server which can
@@ -72,7 +408,7 @@ mod tests {
         });
 
         if let Err(e) = res {
-            panic!(format!("{:?}", e))
+            panic!("{:?}", e)
         }
     }
 
@@ -0,0 +1,180 @@
+use crate::jsonrpc::error::JsonRpcError;
+use rand::Rng;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::time::delay_for;
+
+/// Controls how Contact retries failed requests. After a failed attempt we wait
+/// `base_delay * multiplier^(attempt - 1)`, capped at `max_delay` and randomized
+/// by up to `jitter` of its length, before trying again. Retries never continue
+/// past the timeout of the call being retried.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// the total number of attempts, including the first one. 1 disables retries
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub multiplier: f64,
+    pub max_delay: Duration,
+    /// fraction of each delay to randomize by, between 0 and 1
+    pub jitter: f64,
+    /// decides which errors are worth retrying, JsonRpcError::is_retryable by default
+    pub retryable: fn(&JsonRpcError) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_secs(1),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(30),
+            jitter: 0.2,
+            retryable: JsonRpcError::is_retryable,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that makes every request exactly once
+    pub fn never() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// The delay to wait after the provided attempt (starting at 1) has failed
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        let delay = self.base_delay.mul_f64(exp).min(self.max_delay);
+        if self.jitter > 0.0 {
+            let jitter = rand::thread_rng().gen_range(-self.jitter..=self.jitter);
+            delay.mul_f64(1.0 + jitter)
+        } else {
+            delay
+        }
+    }
+
+    /// Runs the provided request until it succeeds, fails with an error that
+    /// is not retryable, runs out of attempts, or runs out of time. The request
+    /// is passed the time remaining before `timeout` is reached
+    pub async fn run<F, Fut, R>(&self, timeout: Duration, request: F) -> Result<R, JsonRpcError>
+    where
+        F: FnMut(Duration) -> Fut,
+        Fut: Future<Output = Result<R, JsonRpcError>>,
+    {
+        self.run_if(timeout, self.retryable, request).await
+    }
+
+    /// Like run, but `retryable` decides which errors are retried rather than
+    /// the policy, for callers that know more about the request
+    pub(crate) async fn run_if<F, Fut, R, E>(
+        &self,
+        timeout: Duration,
+        retryable: E,
+        mut request: F,
+    ) -> Result<R, JsonRpcError>
+    where
+        F: FnMut(Duration) -> Fut,
+        Fut: Future<Output = Result<R, JsonRpcError>>,
+        E: Fn(&JsonRpcError) -> bool,
+    {
+        let start = Instant::now();
+        let mut attempt = 1;
+        loop {
+            let res = request(timeout - (Instant::now() - start).min(timeout)).await;
+            let e = match res {
+                Ok(val) => return Ok(val),
+                Err(e) => e,
+            };
+            if attempt >= self.max_attempts || !retryable(&e) {
+                return Err(e);
+            }
+            // don't bother waiting if the next attempt would start after the timeout,
+            // a node that is rate limiting us decides the minimum delay
+            let delay = match e.retry_after() {
+                Some(retry_after) => self.delay(attempt).max(retry_after),
+                None => self.delay(attempt),
+            };
+            if Instant::now() - start + delay >= timeout {
+                return Err(e);
+            }
+            trace!(
+                "Request failed with {:?}, retry {} in {:?}",
+                e,
+                attempt,
+                delay
+            );
+            delay_for(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(1),
+            jitter: 0.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy {
+            jitter: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(3), Duration::from_secs(4));
+        assert_eq!(policy.delay(10), Duration::from_secs(30));
+
+        let policy = RetryPolicy::default();
+        let delay = policy.delay(2);
+        assert!(delay >= Duration::from_millis(1600) && delay <= Duration::from_millis(2400));
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let attempts = Cell::new(0);
+        let res = policy(5)
+            .run(Duration::from_secs(10), |_| async {
+                attempts.set(attempts.get() + 1);
+                if attempts.get() < 3 {
+                    Err(JsonRpcError::BadResponse("Server Error 502".to_string()))
+                } else {
+                    Ok(attempts.get())
+                }
+            })
+            .await;
+        assert_eq!(res.unwrap(), 3);
+
+        // running out of attempts returns the last error
+        attempts.set(0);
+        let res: Result<(), _> = policy(2)
+            .run(Duration::from_secs(10), |_| async {
+                attempts.set(attempts.get() + 1);
+                Err(JsonRpcError::BadResponse("Server Error 502".to_string()))
+            })
+            .await;
+        assert!(res.is_err());
+        assert_eq!(attempts.get(), 2);
+
+        // errors that aren't retryable are returned immediately
+        attempts.set(0);
+        let res: Result<(), _> = policy(5)
+            .run(Duration::from_secs(10), |_| async {
+                attempts.set(attempts.get() + 1);
+                Err(JsonRpcError::NoToken)
+            })
+            .await;
+        assert!(res.is_err());
+        assert_eq!(attempts.get(), 1);
+    }
+}
@@ -1,9 +1,8 @@
 use crate::client::Contact;
+use crate::jsonrpc::cosmos_error::CosmosSdkError;
 use crate::jsonrpc::error::JsonRpcError;
 use crate::types::*;
 use crate::utils::maybe_get_optional_tx_info;
-use actix_web::client::ConnectError;
-use actix_web::client::SendRequestError;
 use deep_space::address::Address;
 use deep_space::coin::Coin;
 use deep_space::msg::{Msg, SendMsg};
@@ -14,28 +13,32 @@ use deep_space::transaction::Transaction;
 use deep_space::transaction::TransactionSendType;
 use serde::Deserialize;
 use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::clone::Clone;
 use std::fmt::Debug;
-use std::time::Instant;
-use std::{clone::Clone, time::Duration};
 use tokio::time::delay_for;
 
 impl Contact {
     /// The advanced version of create_and_send transaction that expects you to
-    /// perform your own signing and prep first.
+    /// perform your own signing and prep first. Failed sends are retried by our
+    /// retry policy in every broadcast mode, see retry_on_block
     pub async fn send_transaction<M: Clone + Serialize>(
         &self,
         msg: Transaction<M>,
     ) -> Result<TXSendResponse, JsonRpcError> {
-        self.jsonrpc_client
-            .request_method("txs", Some(msg), self.timeout, None)
-            .await
+        self.retry_on_block(msg).await
     }
 
     /// When a transaction is in 'block' mode it actually asynchronously waits to go into the blockchain
     /// before returning. This is very useful in many contexts but is somewhat limited by the fact that
     /// nodes by default are configured to time out after 10 seconds. The caller of Contact of course
-    /// expects the timeout they provide to be honored. This routine allows us to do that, retrying
-    /// as needed until we reach the specific timeout allowed.
+    /// expects the timeout they provide to be honored. This routine sends the transaction, retrying
+    /// according to our retry policy, until it succeeds or the timeout is reached. Despite the name
+    /// the same retry policy is used for every broadcast mode, block mode also retries responses
+    /// that don't decode as the node may have returned before the transaction was included.
+    ///
+    /// An attempt that got no answer may still have reached the node, so if a retry is rejected
+    /// because the node already has the transaction we wait for it to be included instead.
     pub async fn retry_on_block<
         M: Clone + Serialize,
         T: 'static + for<'de> Deserialize<'de> + Debug,
@@ -43,44 +46,92 @@ impl Contact {
         &self,
         tx: Transaction<M>,
     ) -> Result<T, JsonRpcError> {
-        if let Transaction::Block(..) = tx {
-            let start = Instant::now();
-            let mut res = self
-                .jsonrpc_client
-                .request_method("txs", Some(tx.clone()), self.timeout, None)
-                .await;
-            trace!("Sending tx got {:?}", res);
-            while let Err(JsonRpcError::FailedToSend(SendRequestError::Connect(
-                ConnectError::Disconnected,
-            )))
-            | Err(JsonRpcError::BadResponse(_))
-            | Err(JsonRpcError::BadStruct(_)) = res
-            {
-                // since we can't combine logical statements and destructuring with let bindings
-                // this will have to do
-                if Instant::now() - start > self.timeout {
-                    break;
+        self.check_supported("txs")?;
+        // every attempt and the lookup after it share a single timeout
+        let contact = self.start_operation();
+        let block_mode = matches!(tx, Transaction::Block(_));
+        let retryable = |e: &JsonRpcError| {
+            (self.retry_policy.retryable)(e)
+                || (block_mode && matches!(e, JsonRpcError::BadStruct(_)))
+        };
+        let mut attempts = 0;
+        let res = self
+            .retry_policy
+            .run_if(contact.remaining_time()?, retryable, |_| {
+                attempts += 1;
+                let (contact, tx) = (&contact, &tx);
+                let retry = attempts > 1;
+                async move {
+                    let res = contact.broadcast(tx).await;
+                    trace!("Sending tx got {:?}", res);
+                    match res {
+                        Err(JsonRpcError::SdkError {
+                            error: CosmosSdkError::TxInMempoolCache,
+                            ..
+                        }) if retry => contact.wait_for_tx(tx).await,
+                        res => res,
+                    }
                 }
-                // subtract two durations to get how much time we have left until
-                // the actual user provided timeout. This will be passed as the call timeout
-                // we must consider the case where the remote server does not have a short timeout
-                // but our call fails for some other reason and we then get stuck waiting beyond
-                // the expected timeout duration.
-                let time_left = self.timeout - (Instant::now() - start);
-                delay_for(Duration::from_secs(1)).await;
-                res = self
-                    .jsonrpc_client
-                    .request_method("txs", Some(tx.clone()), time_left, None)
-                    .await;
+            })
+            .await;
+        self.jsonrpc_client
+            .metrics()
+            .record_retries("txs", attempts - 1);
+        res
+    }
+
+    /// Sends a transaction once, with no retries
+    async fn broadcast<M: Clone + Serialize, T: 'static + for<'de> Deserialize<'de> + Debug>(
+        &self,
+        tx: &Transaction<M>,
+    ) -> Result<T, JsonRpcError> {
+        let limit = self.response_limits.for_method("txs");
+        let res = self
+            .jsonrpc_client
+            .request_method("txs", Some(tx), self.remaining_time()?, Some(limit))
+            .await;
+        self.check_deadline(res)
+    }
+
+    /// Waits for a transaction the node already has to be included in a block
+    async fn wait_for_tx<M: Clone + Serialize, T: 'static + for<'de> Deserialize<'de> + Debug>(
+        &self,
+        tx: &Transaction<M>,
+    ) -> Result<T, JsonRpcError> {
+        let txhash = self.tx_hash(tx).await?;
+        info!("Tx {} was already sent, waiting for it", txhash);
+        let none: Option<bool> = None;
+        let mut polls = 1;
+        loop {
+            match self.request(&format!("txs/{}", txhash), none).await {
+                Err(JsonRpcError::HttpError { status: 404, .. }) => {}
+                res => return res,
             }
-            res
-        } else {
-            self.jsonrpc_client
-                .request_method("txs", Some(tx.clone()), self.timeout, None)
-                .await
+            delay_for(self.retry_policy.delay(polls).min(self.remaining_time()?)).await;
+            polls += 1;
         }
     }
 
+    /// The hash the node indexes a transaction by, the hash of its amino encoding
+    /// which only the node can produce
+    async fn tx_hash<M: Clone + Serialize>(
+        &self,
+        tx: &Transaction<M>,
+    ) -> Result<String, JsonRpcError> {
+        let tx = match tx {
+            Transaction::Block(tx) | Transaction::Sync(tx) | Transaction::Async(tx) => tx,
+        };
+        let encoded: EncodeTxResponse = self
+            .request("txs/encode", Some(json!({ "tx": tx })))
+            .await?;
+        let bytes = base64::decode(&encoded.tx)
+            .map_err(|e| JsonRpcError::BadResponse(format!("Invalid encoded tx {}", e)))?;
+        Ok(Sha256::digest(&bytes)
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect())
+    }
+
     /// The hand holding version of send transaction that does it all for you
     #[allow(clippy::too_many_arguments)]
     pub async fn create_and_send_transaction(
@@ -99,8 +150,11 @@ impl Contact {
             .expect("Invalid private key!")
             .to_address();
 
+        // every request made while building and sending this transaction shares
+        // a single timeout rather than each getting the full timeout
+        let contact = self.start_operation();
         let tx_info =
-            maybe_get_optional_tx_info(our_address, chain_id, account_number, sequence, &self)
+            maybe_get_optional_tx_info(our_address, chain_id, account_number, sequence, &contact)
                 .await?;
 
         let std_sign_msg = StdSignMsg {
@@ -126,6 +180,138 @@ impl Contact {
             .unwrap();
         trace!("{}", json!(tx));
 
-        self.retry_on_block(tx).await
+        contact.retry_on_block(tx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{ContactBuilder, RetryPolicy};
+    use crate::jsonrpc::error::TransportError;
+    use crate::jsonrpc::transport::mock::MockTransport;
+    use actix::System;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// sha256 of the bytes 0, 1, 2, which the mock node encodes every tx as
+    const TXHASH: &str = "AE4B3280E56E2FAF83F414A6E3DABE9D5FBE18976544C05FED121ACCB85B53FC";
+    const TX_IN_CACHE: &str = r#"{"code":19,"codespace":"sdk","raw_log":"tx already in mempool"}"#;
+
+    fn send(
+        transport: &Arc<MockTransport>,
+        send_type: TransactionSendType,
+        retry_policy: RetryPolicy,
+    ) -> Result<TXSendResponse, JsonRpcError> {
+        let key = PrivateKey::from_secret(&[1; 32]);
+        let address = key.to_public_key().unwrap().to_address();
+        let coin = Coin {
+            denom: "footoken".to_string(),
+            amount: 1u32.into(),
+        };
+        let msg = StdSignMsg {
+            chain_id: "test".to_string(),
+            account_number: 1,
+            sequence: 1,
+            fee: StdFee {
+                amount: vec![coin.clone()],
+                gas: 500_000u64.into(),
+            },
+            msgs: vec![Msg::SendMsg(SendMsg {
+                from_address: address,
+                to_address: address,
+                amount: vec![coin],
+            })],
+            memo: String::new(),
+        };
+        let tx = key.sign_std_msg(msg, send_type).unwrap();
+        let contact = ContactBuilder::new("http://a", Duration::from_secs(10))
+            .endpoint("http://b")
+            .transport(transport.clone())
+            .retry_policy(retry_policy)
+            .build()
+            .unwrap();
+        System::new("test").block_on(async move { contact.send_transaction(tx).await })
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            base_delay: Duration::from_millis(10),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_block_tx_retried() {
+        let transport = Arc::new(MockTransport::default());
+        transport.fail("http://a/txs", TransportError::Disconnected);
+        transport.respond("http://a/txs", 200, TX_IN_CACHE);
+        transport.respond("http://a/txs/encode", 200, r#"{"tx":"AAEC"}"#);
+        let lookup = format!("http://a/txs/{}", TXHASH);
+        transport.respond(&lookup, 404, r#"{"error":"tx not found"}"#);
+        transport.respond(
+            &lookup,
+            200,
+            &format!(r#"{{"height":"5","txhash":"{}","logs":[]}}"#, TXHASH),
+        );
+
+        // the first attempt got through, so the node rejecting the retry is a success,
+        // the retry only moves on from b because b never received it
+        let res = send(&transport, TransactionSendType::Block, policy()).unwrap();
+        assert_eq!(res.txhash, TXHASH);
+        assert_eq!(
+            *transport.requests.lock().unwrap(),
+            vec![
+                "http://a/txs".to_string(),
+                "http://b/txs".to_string(),
+                "http://a/txs".to_string(),
+                "http://a/txs/encode".to_string(),
+                lookup.clone(),
+                lookup,
+            ]
+        );
+
+        // block mode responses that don't decode are retried too
+        let transport = Arc::new(MockTransport::default());
+        transport.respond("http://a/txs", 200, "{}");
+        transport.respond("http://b/txs", 200, "{}");
+        assert!(matches!(
+            send(&transport, TransactionSendType::Block, policy()),
+            Err(JsonRpcError::BadStruct(_))
+        ));
+        assert_eq!(transport.requests.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_sync_tx_retried() {
+        let sent = format!(r#"{{"txhash":"{}","logs":null}}"#, TXHASH);
+        let transport = Arc::new(MockTransport::default());
+        transport.fail("http://a/txs", TransportError::Disconnected);
+        transport.respond("http://b/txs", 200, &sent);
+        let res = send(&transport, TransactionSendType::Sync, policy()).unwrap();
+        assert_eq!(res.txhash, TXHASH);
+        assert_eq!(transport.requests.lock().unwrap().len(), 2);
+
+        // within an attempt the tx is not sent to another node, the first may have got it
+        let transport = Arc::new(MockTransport::default());
+        transport.fail("http://a/txs", TransportError::Disconnected);
+        transport.respond("http://b/txs", 200, &sent);
+        match send(&transport, TransactionSendType::Sync, RetryPolicy::never()) {
+            Err(JsonRpcError::FailedToSend(TransportError::Disconnected)) => {}
+            res => panic!("Unexpected result {:?}", res),
+        }
+        assert_eq!(transport.requests.lock().unwrap().len(), 1);
+
+        // without an earlier attempt a tx the node already has is left to the caller
+        let transport = Arc::new(MockTransport::default());
+        transport.respond("http://a/txs", 200, TX_IN_CACHE);
+        match send(&transport, TransactionSendType::Async, policy()) {
+            Err(JsonRpcError::SdkError {
+                error: CosmosSdkError::TxInMempoolCache,
+                ..
+            }) => {}
+            res => panic!("Unexpected result {:?}", res),
+        }
+        assert_eq!(transport.requests.lock().unwrap().len(), 1);
     }
 }
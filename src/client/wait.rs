@@ -0,0 +1,195 @@
+use crate::client::Contact;
+use crate::jsonrpc::error::JsonRpcError;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tokio::time::delay_for;
+
+/// How often the wait_for helpers poll the node
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The result of a single poll by one of the wait_for helpers
+enum Status<T> {
+    Done(T),
+    /// not there yet, with the latest height the node reported if we know it
+    Waiting(Option<u64>),
+}
+
+impl Contact {
+    /// Waits for the chain to produce its first block, returning its height.
+    /// Fails with JsonRpcError::WaitTimeout if that takes longer than `timeout`
+    pub async fn wait_for_chain_start(&self, timeout: Duration) -> Result<u64, JsonRpcError> {
+        self.wait_until("the chain to start", timeout, |contact| async move {
+            match contact.get_latest_block().await?.block {
+                Some(block) => Ok(Status::Done(block.height())),
+                None => Ok(Status::Waiting(None)),
+            }
+        })
+        .await
+    }
+
+    /// Waits for the node to finish syncing, returning the latest height once
+    /// it has. Fails with JsonRpcError::WaitTimeout if that takes longer than
+    /// `timeout`
+    pub async fn wait_for_sync(&self, timeout: Duration) -> Result<u64, JsonRpcError> {
+        self.wait_until("the node to sync", timeout, |contact| async move {
+            let syncing = contact.get_syncing_status().await?.syncing;
+            let height = match contact.get_latest_block().await?.block {
+                Some(block) => Some(block.height()),
+                None if syncing => None,
+                None => return Err(JsonRpcError::ChainNotRunning),
+            };
+            match (syncing, height) {
+                (false, Some(height)) => Ok(Status::Done(height)),
+                (_, height) => Ok(Status::Waiting(height)),
+            }
+        })
+        .await
+    }
+
+    /// Waits for the chain to reach at least the provided height, returning the
+    /// height reached. Fails with JsonRpcError::WaitTimeout if that takes longer
+    /// than `timeout`
+    pub async fn wait_for_height(
+        &self,
+        height: u64,
+        timeout: Duration,
+    ) -> Result<u64, JsonRpcError> {
+        let condition = format!("height {}", height);
+        self.wait_until(&condition, timeout, |contact| async move {
+            match contact.get_latest_block().await?.block {
+                Some(block) if block.height() >= height => Ok(Status::Done(block.height())),
+                Some(block) => Ok(Status::Waiting(Some(block.height()))),
+                None => Ok(Status::Waiting(None)),
+            }
+        })
+        .await
+    }
+
+    /// Polls `check` until it is done or the timeout passes, progress is logged
+    /// at info. Errors that may go away, like the node being unreachable or the
+    /// chain not having started, are logged and polling continues, any other
+    /// error is returned immediately
+    async fn wait_until<T, F, Fut>(
+        &self,
+        condition: &str,
+        timeout: Duration,
+        mut check: F,
+    ) -> Result<T, JsonRpcError>
+    where
+        F: FnMut(Contact) -> Fut,
+        Fut: Future<Output = Result<Status<T>, JsonRpcError>>,
+    {
+        let start = Instant::now();
+        let contact = self.with_deadline(start + timeout);
+        let mut last_height = None;
+        let mut last_error = None;
+        loop {
+            match check(contact.clone()).await {
+                Ok(Status::Done(val)) => return Ok(val),
+                Ok(Status::Waiting(height)) => {
+                    info!(
+                        "Waiting for {}, latest height {:?}, waited {}s",
+                        condition,
+                        height,
+                        start.elapsed().as_secs()
+                    );
+                    last_height = height.or(last_height);
+                    last_error = None;
+                }
+                Err(JsonRpcError::DeadlineExceeded) => {}
+                Err(e) if e.is_retryable() || e.is_node_failure() || is_waiting(&e) => {
+                    info!("Waiting for {}, got {}", condition, e);
+                    last_error = Some(Box::new(e));
+                }
+                Err(e) => return Err(e),
+            }
+            match contact.remaining_time() {
+                Ok(remaining) => delay_for(remaining.min(POLL_INTERVAL)).await,
+                Err(_) => {
+                    return Err(JsonRpcError::WaitTimeout {
+                        condition: condition.to_string(),
+                        last_height,
+                        last_error,
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// Errors that mean the node is not ready yet, rather than that it is broken
+fn is_waiting(e: &JsonRpcError) -> bool {
+    matches!(e, JsonRpcError::ChainNotRunning | JsonRpcError::NodeSyncing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonrpc::error::TransportError;
+    use crate::jsonrpc::transport::mock::{test_contact, MockTransport};
+    use actix::System;
+    use std::sync::Arc;
+
+    const GENESIS: &str = r#"{"block_id":{"hash":"","parts":{"total":0,"hash":""}},"block":null}"#;
+    const BLOCK: &str = include_str!("../../test_files/test_block_althea_testnet1v5.json");
+
+    #[test]
+    fn test_wait_for_chain_start() {
+        let transport = Arc::new(MockTransport::default());
+        transport.fail(
+            "http://a/blocks/latest",
+            TransportError::Connect("refused".to_string()),
+        );
+        transport.respond("http://a/blocks/latest", 200, GENESIS);
+        transport.respond("http://a/blocks/latest", 200, BLOCK);
+        let contact = test_contact(&["http://a"], transport.clone());
+
+        System::new("test").block_on(async move {
+            let height = contact
+                .wait_for_chain_start(Duration::from_secs(10))
+                .await
+                .unwrap();
+            assert_eq!(height, 489421);
+            assert_eq!(transport.requests.lock().unwrap().len(), 3);
+            assert_eq!(contact.get_latest_block_number().await.unwrap(), height);
+            assert_eq!(
+                contact
+                    .wait_for_height(489000, Duration::from_secs(10))
+                    .await
+                    .unwrap(),
+                489421
+            );
+        });
+    }
+
+    #[test]
+    fn test_wait_timeout() {
+        let transport = Arc::new(MockTransport::default());
+        transport.respond("http://a/syncing", 200, r#"{"syncing":true}"#);
+        transport.respond("http://a/blocks/latest", 200, BLOCK);
+        let contact = test_contact(&["http://a"], transport.clone());
+
+        System::new("test").block_on(async move {
+            let start = Instant::now();
+            match contact.wait_for_sync(Duration::from_millis(100)).await {
+                Err(JsonRpcError::WaitTimeout {
+                    last_height: Some(489421),
+                    last_error: None,
+                    ..
+                }) => {}
+                res => panic!("Unexpected result {:?}", res),
+            }
+            assert!(start.elapsed() < Duration::from_secs(1));
+
+            match contact
+                .wait_for_height(500000, Duration::from_millis(100))
+                .await
+            {
+                Err(JsonRpcError::WaitTimeout { condition, .. }) => {
+                    assert_eq!(condition, "height 500000")
+                }
+                res => panic!("Unexpected result {:?}", res),
+            }
+        });
+    }
+}
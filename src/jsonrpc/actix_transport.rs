@@ -0,0 +1,173 @@
+use crate::jsonrpc::error::{JsonRpcError, TransportError};
+use crate::jsonrpc::transport::{parse_retry_after, Transport, TransportConfig, TransportResponse};
+use actix_web::client::{
+    Client, ClientBuilder, ClientRequest, ClientResponse, ConnectError, Connector, PayloadError,
+    SendRequestError,
+};
+use actix_web::http::header;
+use actix_web::web::Bytes;
+use async_trait::async_trait;
+use futures::Stream;
+use openssl::pkey::PKey;
+use openssl::ssl::{SslConnector, SslMethod};
+use openssl::x509::X509;
+use std::time::Duration;
+
+/// A Transport backed by the actix-web client, this must be used from
+/// within a running actix System
+#[derive(Default)]
+pub struct ActixTransport {
+    client: Client,
+}
+
+impl ActixTransport {
+    pub fn new(client: Client) -> Self {
+        ActixTransport { client }
+    }
+
+    /// Creates an actix client with the provided settings, proxies are not
+    /// supported by the actix client so setting one is an error
+    pub fn from_config(config: &TransportConfig) -> Result<Self, JsonRpcError> {
+        if config.proxy.is_some() {
+            return Err(JsonRpcError::BadInput(
+                "The actix transport does not support proxies, use the hyper-transport feature"
+                    .to_string(),
+            ));
+        }
+        let mut connector = Connector::new();
+        if config.root_certificate.is_some() || config.client_certificate.is_some() {
+            connector =
+                connector.ssl(ssl_connector(config).map_err(|e| {
+                    JsonRpcError::BadInput(format!("Invalid TLS configuration {}", e))
+                })?);
+        }
+        if let Some(limit) = config.max_connections {
+            connector = connector.limit(limit);
+        }
+        if let Some(idle_timeout) = config.idle_timeout {
+            connector = connector.conn_keep_alive(idle_timeout);
+        }
+        let client = ClientBuilder::new().connector(connector.finish()).finish();
+        Ok(ActixTransport { client })
+    }
+}
+
+fn ssl_connector(config: &TransportConfig) -> Result<SslConnector, openssl::error::ErrorStack> {
+    // starts out trusting the system roots, we only add to them
+    let mut builder = SslConnector::builder(SslMethod::tls())?;
+    if let Some(root) = &config.root_certificate {
+        builder.cert_store_mut().add_cert(X509::from_pem(root)?)?;
+    }
+    if let Some(cert) = &config.client_certificate {
+        let certificate = X509::from_pem(&cert.certificate)?;
+        let private_key = PKey::private_key_from_pem(&cert.private_key)?;
+        builder.set_certificate(&certificate)?;
+        builder.set_private_key(&private_key)?;
+    }
+    Ok(builder.build())
+}
+
+fn with_headers(mut request: ClientRequest, headers: &[(String, String)]) -> ClientRequest {
+    for (key, value) in headers {
+        request = request.set_header(key.as_str(), value.as_str());
+    }
+    request
+}
+
+#[async_trait(?Send)]
+impl Transport for ActixTransport {
+    async fn get(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        timeout: Duration,
+        limit: usize,
+    ) -> Result<TransportResponse, TransportError> {
+        let request = self
+            .client
+            .get(url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .timeout(timeout);
+        let res = with_headers(request, headers).send().await;
+        read_response(res, limit).await
+    }
+
+    async fn post_json(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        body: Vec<u8>,
+        timeout: Duration,
+        limit: usize,
+    ) -> Result<TransportResponse, TransportError> {
+        let request = self
+            .client
+            .post(url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .timeout(timeout);
+        let res = with_headers(request, headers).send_body(body).await;
+        read_response(res, limit).await
+    }
+}
+
+async fn read_response<S>(
+    res: Result<ClientResponse<S>, SendRequestError>,
+    limit: usize,
+) -> Result<TransportResponse, TransportError>
+where
+    S: Stream<Item = Result<Bytes, PayloadError>> + Unpin,
+{
+    let mut res = res?;
+    let status = res.status().as_u16();
+    let retry_after = res
+        .headers()
+        .get(header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_retry_after);
+    let body = res.body().limit(limit).await?;
+    Ok(TransportResponse {
+        status,
+        body: body.to_vec(),
+        retry_after,
+    })
+}
+
+impl From<SendRequestError> for TransportError {
+    fn from(error: SendRequestError) -> Self {
+        match error {
+            SendRequestError::Connect(ConnectError::Disconnected) => TransportError::Disconnected,
+            SendRequestError::Connect(e) => TransportError::Connect(e.to_string()),
+            SendRequestError::Timeout => TransportError::Timeout,
+            e => TransportError::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<PayloadError> for TransportError {
+    fn from(error: PayloadError) -> Self {
+        match error {
+            PayloadError::Overflow => TransportError::Overflow,
+            e => TransportError::Body(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy_unsupported() {
+        let config = TransportConfig {
+            proxy: Some("http://localhost:3128".to_string()),
+            ..Default::default()
+        };
+        assert!(ActixTransport::from_config(&config).is_err());
+
+        let config = TransportConfig {
+            root_certificate: Some(b"not a certificate".to_vec()),
+            ..Default::default()
+        };
+        assert!(ActixTransport::from_config(&config).is_err());
+    }
+}
@@ -1,26 +1,134 @@
-use crate::{jsonrpc::error::JsonRpcError, types::TxSendErrorResponse};
-use actix_web::client::Client;
-use actix_web::http::header;
+use crate::jsonrpc::endpoint::{by_health, Endpoint, EndpointHealth};
+use crate::jsonrpc::interceptor::{InterceptedRequest, InterceptedResponse, Interceptor};
+use crate::jsonrpc::metrics::Metrics;
+use crate::jsonrpc::rate_limit::{Limiter, RateLimit};
+use crate::jsonrpc::transport::{DefaultTransport, Transport, TransportResponse};
+use crate::{
+    jsonrpc::error::{JsonRpcError, TransportError},
+    types::{SyncingStatus, TxSendErrorResponse},
+};
+use futures::future::join_all;
+use log::Level;
 use serde::{Deserialize, Serialize};
-use serde_json::from_value;
 use serde_json::Value;
 use std::str;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 pub struct HTTPClient {
-    url: String,
-    client: Client,
+    endpoints: Vec<Endpoint>,
+    transport: Box<dyn Transport>,
+    /// sent with every request, see add_header
+    headers: Vec<(String, String)>,
+    /// run around every request in order, see add_interceptor
+    interceptors: Vec<Box<dyn Interceptor>>,
+    metrics: Metrics,
+    /// the most time a single endpoint gets before we fail over, see set_attempt_timeout
+    attempt_timeout: Option<Duration>,
+    /// how often the syncing status of each endpoint is checked, see
+    /// set_health_check_interval
+    health_check_interval: Option<Duration>,
 }
 
 impl HTTPClient {
     pub fn new(url: &str) -> Self {
+        Self::with_transport(url, DefaultTransport::default())
+    }
+
+    /// Creates an HTTPClient that makes its requests using the provided transport
+    pub fn with_transport<T: Transport + 'static>(url: &str, transport: T) -> Self {
+        Self::with_endpoints(&[url], transport)
+    }
+
+    /// Creates an HTTPClient that routes each request to the healthiest of the
+    /// provided urls, failing over to the next healthiest if a node can't be
+    /// reached or returns a bad response. Panics if `urls` is empty,
+    /// ContactBuilder::build returns an error instead
+    pub fn with_endpoints<T: Transport + 'static>(urls: &[&str], transport: T) -> Self {
+        assert!(!urls.is_empty(), "HTTPClient requires at least one url!");
         Self {
-            url: url.to_string(),
-            client: Client::default(),
+            endpoints: urls.iter().map(|url| Endpoint::new(url)).collect(),
+            transport: Box::new(transport),
+            headers: Vec::new(),
+            interceptors: Vec::new(),
+            metrics: Metrics::default(),
+            attempt_timeout: None,
+            health_check_interval: None,
+        }
+    }
+
+    /// Adds a header that is sent with every request, for things like api keys
+    /// or bearer tokens. Credentials in an endpoint url take precedence over
+    /// an Authorization header set here for requests to that endpoint
+    pub fn add_header(&mut self, key: &str, value: &str) {
+        self.headers.push((key.to_string(), value.to_string()));
+    }
+
+    /// Limits the requests sent to each endpoint, replacing any previous limit
+    pub fn set_rate_limit(&mut self, limit: RateLimit) {
+        for endpoint in self.endpoints.iter_mut() {
+            endpoint.limiter = Limiter::new(limit);
+        }
+    }
+
+    /// Adds an interceptor that sees and may modify every request made by this
+    /// client after the interceptors already added, see Interceptor
+    pub fn add_interceptor<I: Interceptor + 'static>(&mut self, interceptor: I) {
+        self.interceptors.push(Box::new(interceptor));
+    }
+
+    /// Limits how long each endpoint gets to answer a request before we fail
+    /// over to the next one. By default the time left is split evenly between
+    /// the endpoints not yet tried, so one hung node can't use all of it
+    pub fn set_attempt_timeout(&mut self, timeout: Option<Duration>) {
+        self.attempt_timeout = timeout;
+    }
+
+    /// When set, the syncing status of an endpoint is checked before it is
+    /// used if it was last checked longer than `interval` ago, so nodes that
+    /// fall behind are avoided and nodes that catch up are used again
+    pub fn set_health_check_interval(&mut self, interval: Option<Duration>) {
+        self.health_check_interval = interval;
+    }
+
+    /// The headers for a request to the provided endpoint
+    fn headers_for(&self, endpoint: &Endpoint) -> Vec<(String, String)> {
+        match &endpoint.authorization {
+            Some(auth) => self
+                .headers
+                .iter()
+                .filter(|(key, _)| !key.eq_ignore_ascii_case("authorization"))
+                .cloned()
+                .chain(std::iter::once(("Authorization".to_string(), auth.clone())))
+                .collect(),
+            None => self.headers.clone(),
         }
     }
 
-    pub async fn request_method<T: Serialize, R: 'static>(
+    /// The url and current health statistics for each endpoint, in the order
+    /// they were provided
+    pub fn endpoints(&self) -> Vec<(String, EndpointHealth)> {
+        self.endpoints
+            .iter()
+            .map(|e| (e.url.clone(), e.health()))
+            .collect()
+    }
+
+    /// Request metrics for every endpoint, see Metrics::to_prometheus
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    pub(crate) fn endpoints_slice(&self) -> &[Endpoint] {
+        &self.endpoints
+    }
+
+    /// Marks an endpoint as syncing, syncing endpoints only get requests
+    /// when every other endpoint is also syncing
+    pub fn set_syncing(&self, endpoint: usize, syncing: bool) {
+        self.endpoints[endpoint].set_syncing(syncing)
+    }
+
+    pub async fn request_method<T: Serialize, R>(
         &self,
         method: &str,
         params: Option<T>,
@@ -29,68 +137,423 @@ impl HTTPClient {
     ) -> Result<R, JsonRpcError>
     where
         for<'de> R: Deserialize<'de>,
-        // T: std::fmt::Debug,
-        R: std::fmt::Debug,
+        R: std::fmt::Debug + 'static,
     {
-        trace!(
-            "About to make contact request to {} with payload {}",
+        let body = serialize_params(params)?;
+        if log_enabled!(Level::Trace) {
+            trace!(
+                "About to make contact request to {} with payload {}",
+                method,
+                String::from_utf8_lossy(body.as_deref().unwrap_or_default())
+            );
+        }
+        let start = Instant::now();
+        self.check_syncing(timeout).await;
+        // we always have an endpoint, so this is only returned if checking which
+        // nodes are syncing used up the time before any of them could be tried
+        let mut res = Err(JsonRpcError::DeadlineExceeded);
+        let endpoints = by_health(&self.endpoints);
+        for (tried, endpoint) in endpoints.iter().enumerate() {
+            // every attempt shares the timeout the caller gave us
+            let elapsed = Instant::now() - start;
+            if elapsed >= timeout {
+                break;
+            }
+            let remaining = timeout - elapsed;
+            let attempt_timeout = match self.attempt_timeout {
+                Some(attempt_timeout) => attempt_timeout.min(remaining),
+                None => remaining / (endpoints.len() - tried) as u32,
+            };
+            let endpoint = &self.endpoints[*endpoint];
+            res = self
+                .request_endpoint(
+                    endpoint,
+                    method,
+                    body.clone(),
+                    attempt_timeout,
+                    request_size_limit,
+                )
+                .await;
+            match res {
+                // a post, like a transaction, may have been acted on even if we got no
+                // answer, so it only goes to another node if it never reached this one
+                Err(ref e) if e.is_node_failure() && (body.is_none() || !delivered(e)) => {
+                    warn!("Request {} to {} failed {:?}", method, endpoint.url, res)
+                }
+                _ => return res,
+            }
+        }
+        res
+    }
+
+    /// Checks the syncing status of every endpoint that has not been checked
+    /// within the health check interval, if one is set. Nodes that don't answer
+    /// keep their previous status and are left to the normal failover
+    pub(crate) async fn check_syncing(&self, timeout: Duration) {
+        let interval = match self.health_check_interval {
+            Some(interval) => interval,
+            None => return,
+        };
+        // a hung node gets the same share of the time it would get for a request
+        let timeout = match self.attempt_timeout {
+            Some(attempt_timeout) => attempt_timeout.min(timeout),
+            None => timeout / self.endpoints.len() as u32,
+        };
+        let none: Option<bool> = None;
+        let checks = self
+            .endpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, endpoint)| endpoint.syncing_stale(interval))
+            .map(|(i, _)| async move {
+                let res = self
+                    .request_method_on::<_, SyncingStatus>(i, "syncing", none, timeout, None)
+                    .await;
+                let endpoint = &self.endpoints[i];
+                match res {
+                    Ok(status) => endpoint.set_syncing(status.syncing),
+                    Err(e) => {
+                        warn!("Failed to check if {} is syncing {:?}", endpoint.url, e);
+                        endpoint.set_syncing(endpoint.health().syncing)
+                    }
+                }
+            });
+        join_all(checks).await;
+    }
+
+    /// Makes a request to one specific endpoint with no failover, `endpoint` is
+    /// the index of the url as provided when this client was created
+    pub async fn request_method_on<T: Serialize, R>(
+        &self,
+        endpoint: usize,
+        method: &str,
+        params: Option<T>,
+        timeout: Duration,
+        request_size_limit: Option<usize>,
+    ) -> Result<R, JsonRpcError>
+    where
+        for<'de> R: Deserialize<'de>,
+        R: std::fmt::Debug + 'static,
+    {
+        let body = serialize_params(params)?;
+        self.request_endpoint(
+            &self.endpoints[endpoint],
             method,
-            json!(params)
-        );
-        // the response payload size limit for this request, almost everything
-        // will set this to None, and get the default 1mb, but some requests
-        // need bigger buffers (like full block requests)
+            body,
+            timeout,
+            request_size_limit,
+        )
+        .await
+    }
+
+    /// Sends a request to an endpoint and records the outcome in its health
+    /// stats and our metrics
+    async fn request_endpoint<R>(
+        &self,
+        endpoint: &Endpoint,
+        method: &str,
+        body: Option<Vec<u8>>,
+        timeout: Duration,
+        request_size_limit: Option<usize>,
+    ) -> Result<R, JsonRpcError>
+    where
+        for<'de> R: Deserialize<'de>,
+        R: std::fmt::Debug + 'static,
+    {
+        // time spent waiting for our turn counts against the timeout
+        let start = Instant::now();
+        let _permit = match tokio::time::timeout(timeout, endpoint.limiter.acquire()).await {
+            Ok(permit) => permit,
+            Err(_) => return Err(JsonRpcError::FailedToSend(TransportError::Timeout)),
+        };
+        let timeout = timeout - (Instant::now() - start).min(timeout);
+
+        let start = Instant::now();
+        let (res, sent) = self
+            .send(endpoint, method, body, timeout, request_size_limit)
+            .await;
+        if !sent {
+            // an interceptor answered for the node, so this says nothing about it
+            return res;
+        }
+        let latency = Instant::now() - start;
+        if let Err(JsonRpcError::RateLimited { retry_after, .. }) = res {
+            // hold back everyone else's requests to this node too
+            endpoint
+                .limiter
+                .pause(retry_after.unwrap_or_else(|| Duration::from_secs(1)));
+        }
+        match res {
+            Err(ref e) if e.is_node_failure() => endpoint.record_failure(),
+            _ => endpoint.record_success(latency),
+        }
+        self.metrics
+            .record_request(&endpoint.url, method, latency, res.as_ref().map(|_| ()));
+        res
+    }
+
+    /// Sends a request through our interceptors, returning the result and
+    /// whether the request was actually sent rather than short circuited
+    async fn send<R>(
+        &self,
+        endpoint: &Endpoint,
+        method: &str,
+        body: Option<Vec<u8>>,
+        timeout: Duration,
+        request_size_limit: Option<usize>,
+    ) -> (Result<R, JsonRpcError>, bool)
+    where
+        for<'de> R: Deserialize<'de>,
+        R: std::fmt::Debug + 'static,
+    {
+        // the response payload size limit for this request, Contact sets this
+        // from its ResponseLimits, anyone else passing None gets the default 1mb
         let limit = request_size_limit.unwrap_or(1_000_000);
-        let url_with_method = format!("{}/{}", self.url, method);
+        let url = format!("{}/{}", endpoint.url, method);
+        let headers = self.headers_for(endpoint);
+        if self.interceptors.is_empty() {
+            let res = self
+                .send_raw(&endpoint.url, url, &headers, body, timeout, limit)
+                .await;
+            return (res.and_then(|res| decode_response(&res.body)), true);
+        }
+
+        // interceptors work with the response as a Value, so it's decoded twice
+        let mut request = InterceptedRequest {
+            url,
+            method: method.to_string(),
+            headers,
+            body,
+        };
+        let mut short_circuit = None;
+        for interceptor in self.interceptors.iter() {
+            short_circuit = interceptor.before_request(&mut request);
+            if short_circuit.is_some() {
+                break;
+            }
+        }
+        let sent = short_circuit.is_none();
+        let start = Instant::now();
+        let (status, result) = match short_circuit {
+            Some(result) => (None, result),
+            None => {
+                let res = self
+                    .send_raw(
+                        &endpoint.url,
+                        request.url.clone(),
+                        &request.headers,
+                        request.body.clone(),
+                        timeout,
+                        limit,
+                    )
+                    .await;
+                match res {
+                    Ok(res) => (Some(res.status), decode_response(&res.body)),
+                    Err(e) => (e.status(), Err(e)),
+                }
+            }
+        };
+        let mut response = InterceptedResponse {
+            status,
+            latency: Instant::now() - start,
+            result,
+        };
+        for interceptor in self.interceptors.iter() {
+            interceptor.after_response(&request, &mut response);
+        }
+        (response.result.and_then(decode_value), sent)
+    }
+
+    /// Sends a request using our transport, returning the response if it has a success status
+    async fn send_raw(
+        &self,
+        endpoint: &str,
+        url: String,
+        headers: &[(String, String)],
+        body: Option<Vec<u8>>,
+        timeout: Duration,
+        limit: usize,
+    ) -> Result<TransportResponse, JsonRpcError> {
         // if we don't have a payload this is a get request
-        let res = if let Some(params) = params {
-            self.client
-                .post(&url_with_method)
-                .header(header::CONTENT_TYPE, "application/json")
-                .timeout(timeout)
-                .send_json(&params)
+        let res = if let Some(body) = body {
+            self.transport
+                .post_json(&url, headers, body, timeout, limit)
                 .await
         } else {
-            self.client
-                .get(&url_with_method)
-                .header(header::CONTENT_TYPE, "application/json")
-                .timeout(timeout)
-                .send()
-                .await
+            self.transport.get(&url, headers, timeout, limit).await
         };
-        let mut res = match res {
-            Ok(val) => val,
+        let res = match res {
+            Ok(val) => {
+                self.metrics.record_bytes(endpoint, val.body.len());
+                val
+            }
+            Err(TransportError::Overflow) => {
+                return Err(JsonRpcError::ResponseTooLarge {
+                    limit,
+                    endpoint: url,
+                })
+            }
+            Err(TransportError::Body(e)) => return Err(JsonRpcError::BadResponse(e)),
             Err(e) => return Err(JsonRpcError::FailedToSend(e)),
         };
-        let status = res.status();
-        if !status.is_success() {
-            return Err(JsonRpcError::BadResponse(format!(
-                "Server Error {}",
-                status
-            )));
-        }
-
-        // this layer of error handling is not technically required, you could
-        // replace this layer with a direct parse into Result<R, Error> but that's
-        // much harder to debug since there's no way to actually display serde value
-        // you're looking for.
-        let json_value: Result<Value, _> = res.json().limit(limit).await;
-        trace!("got Cosmos JSONRPC response {:#?}", json_value);
-        let json: Value = match json_value {
-            Ok(val) => val,
-            Err(e) => return Err(JsonRpcError::BadResponse(e.to_string())),
-        };
-        let data: R = match from_value(json.clone()) {
-            Ok(val) => val,
-            Err(e) => {
-                if let Ok(bad_tx_response) = from_value(json) {
-                    let bad_tx_response: TxSendErrorResponse = bad_tx_response;
-                    return Err(JsonRpcError::BadStruct(bad_tx_response.raw_log));
-                }
-                return Err(JsonRpcError::BadStruct(e.to_string()));
+        if res.status == 429 {
+            return Err(JsonRpcError::RateLimited {
+                retry_after: res.retry_after,
+                body: String::from_utf8_lossy(&res.body).to_string(),
+            });
+        }
+        if !res.is_success() {
+            // the body of an error response often explains the failure, so we keep it
+            let cosmos_error: Option<TxSendErrorResponse> = serde_json::from_slice(&res.body).ok();
+            return Err(JsonRpcError::HttpError {
+                status: res.status,
+                body: String::from_utf8_lossy(&res.body).to_string(),
+                cosmos_error,
+            });
+        }
+        Ok(res)
+    }
+}
+
+/// Decodes a successful response body into `R` in a single pass over the bytes.
+/// Only if that fails is the body checked for a Cosmos SDK error, which nodes
+/// return with a 200 status for rejected transactions
+pub fn decode_response<R>(body: &[u8]) -> Result<R, JsonRpcError>
+where
+    for<'de> R: Deserialize<'de>,
+{
+    if log_enabled!(Level::Trace) {
+        trace!(
+            "got Cosmos JSONRPC response {}",
+            String::from_utf8_lossy(body)
+        );
+    }
+    match serde_json::from_slice(body) {
+        Ok(val) => Ok(val),
+        Err(e) if e.is_syntax() || e.is_eof() => Err(JsonRpcError::BadResponse(e.to_string())),
+        Err(e) => match serde_json::from_slice::<TxSendErrorResponse>(body) {
+            Ok(bad_tx_response) => Err(JsonRpcError::SdkError {
+                error: (&bad_tx_response).into(),
+                response: Box::new(bad_tx_response),
+            }),
+            Err(_) => Err(JsonRpcError::BadStruct(e.to_string())),
+        },
+    }
+}
+
+/// Decodes a response that has already been parsed into a Value, see decode_response
+pub(crate) fn decode_value<R>(value: Value) -> Result<R, JsonRpcError>
+where
+    for<'de> R: Deserialize<'de>,
+{
+    match R::deserialize(&value) {
+        Ok(val) => Ok(val),
+        Err(e) => match TxSendErrorResponse::deserialize(&value) {
+            Ok(bad_tx_response) => Err(JsonRpcError::SdkError {
+                error: (&bad_tx_response).into(),
+                response: Box::new(bad_tx_response),
+            }),
+            Err(_) => Err(JsonRpcError::BadStruct(e.to_string())),
+        },
+    }
+}
+
+/// Whether a request that failed with the provided error may have been received
+/// by the node
+fn delivered(e: &JsonRpcError) -> bool {
+    !matches!(
+        e,
+        JsonRpcError::FailedToSend(TransportError::Connect(_)) | JsonRpcError::RateLimited { .. }
+    )
+}
+
+fn serialize_params<T: Serialize>(params: Option<T>) -> Result<Option<Vec<u8>>, JsonRpcError> {
+    match params {
+        Some(params) => match serde_json::to_vec(&params) {
+            Ok(val) => Ok(Some(val)),
+            Err(e) => Err(JsonRpcError::BadInput(e.to_string())),
+        },
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonrpc::cosmos_error::CosmosSdkError;
+    use crate::jsonrpc::transport::mock::MockTransport;
+    use crate::types::SyncingStatus;
+    use actix::System;
+
+    fn request(status: u16, body: &str) -> Result<SyncingStatus, JsonRpcError> {
+        let transport = MockTransport::default();
+        transport.respond("http://localhost/syncing", status, body);
+        let client = HTTPClient::with_transport("http://localhost", transport);
+        System::new("test").block_on(async move {
+            client
+                .request_method("syncing", None::<bool>, Duration::from_secs(1), None)
+                .await
+        })
+    }
+
+    #[test]
+    fn test_custom_transport() {
+        let res = request(200, r#"{"syncing":true}"#).unwrap();
+        assert!(res.syncing);
+
+        match request(500, "") {
+            Err(JsonRpcError::HttpError { status: 500, .. }) => {}
+            res => panic!("Unexpected result {:?}", res),
+        }
+
+        let body = r#"{"code":9,"codespace":"sdk","raw_log":"unknown address"}"#;
+        match request(404, body) {
+            Err(JsonRpcError::HttpError {
+                status: 404,
+                body: res_body,
+                cosmos_error: Some(cosmos_error),
+            }) => {
+                assert_eq!(res_body, body);
+                assert_eq!(cosmos_error.code, 9);
+                assert_eq!(cosmos_error.raw_log, "unknown address");
+                assert_eq!(
+                    JsonRpcError::HttpError {
+                        status: 404,
+                        body: res_body,
+                        cosmos_error: Some(cosmos_error)
+                    }
+                    .sdk_error(),
+                    Some(CosmosSdkError::UnknownAddress)
+                );
             }
-        };
+            res => panic!("Unexpected result {:?}", res),
+        }
 
-        Ok(data)
+        let tx_error = r#"{"code":5,"codespace":"sdk","raw_log":"insufficient funds"}"#;
+        match request(200, tx_error) {
+            Err(JsonRpcError::SdkError { error, response }) => {
+                assert_eq!(error, CosmosSdkError::InsufficientFunds);
+                assert_eq!(response.raw_log, "insufficient funds");
+            }
+            res => panic!("Unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_response_too_large() {
+        let transport = MockTransport::default();
+        transport.respond("http://localhost/syncing", 200, r#"{"syncing":true}"#);
+        let client = HTTPClient::with_transport("http://localhost", transport);
+        let res: Result<SyncingStatus, _> = System::new("test").block_on(async move {
+            client
+                .request_method("syncing", None::<bool>, Duration::from_secs(1), Some(4))
+                .await
+        });
+        match res {
+            Err(JsonRpcError::ResponseTooLarge { limit: 4, endpoint }) => {
+                assert_eq!(endpoint, "http://localhost/syncing")
+            }
+            res => panic!("Unexpected result {:?}", res),
+        }
     }
 }
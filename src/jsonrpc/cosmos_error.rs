@@ -0,0 +1,158 @@
+use crate::types::TxSendErrorResponse;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result;
+
+/// The codespace used by errors from the Cosmos SDK itself, as opposed to
+/// errors defined by individual modules
+pub const SDK_CODESPACE: &str = "sdk";
+
+/// Typed versions of the Cosmos SDK's own error codes, decoded from the
+/// `codespace`, `code` and `raw_log` the node returns when it rejects a tx
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CosmosSdkError {
+    InvalidSequence {
+        expected: Option<u64>,
+        got: Option<u64>,
+    },
+    Unauthorized,
+    InsufficientFunds,
+    InvalidAddress,
+    UnknownAddress,
+    OutOfGas {
+        wanted: Option<u64>,
+        used: Option<u64>,
+    },
+    InsufficientFee,
+    TxInMempoolCache,
+    MempoolIsFull,
+    /// Any error we don't have a variant for, including all module errors
+    Other {
+        codespace: String,
+        code: u64,
+    },
+}
+
+impl CosmosSdkError {
+    pub fn new(codespace: &str, code: u64, raw_log: &str, gas_used: Option<u64>) -> Self {
+        if codespace != SDK_CODESPACE {
+            return CosmosSdkError::Other {
+                codespace: codespace.to_string(),
+                code,
+            };
+        }
+        match code {
+            3 => CosmosSdkError::InvalidSequence {
+                expected: number_after(raw_log, "expected "),
+                got: number_after(raw_log, "got "),
+            },
+            4 => CosmosSdkError::Unauthorized,
+            5 => CosmosSdkError::InsufficientFunds,
+            7 => CosmosSdkError::InvalidAddress,
+            9 => CosmosSdkError::UnknownAddress,
+            11 => CosmosSdkError::OutOfGas {
+                wanted: number_after(raw_log, "gasWanted: "),
+                used: number_after(raw_log, "gasUsed: ").or(gas_used),
+            },
+            13 => CosmosSdkError::InsufficientFee,
+            19 => CosmosSdkError::TxInMempoolCache,
+            20 => CosmosSdkError::MempoolIsFull,
+            _ => CosmosSdkError::Other {
+                codespace: codespace.to_string(),
+                code,
+            },
+        }
+    }
+}
+
+impl From<&TxSendErrorResponse> for CosmosSdkError {
+    fn from(response: &TxSendErrorResponse) -> Self {
+        CosmosSdkError::new(
+            &response.codespace,
+            response.code,
+            &response.raw_log,
+            response.gas_used,
+        )
+    }
+}
+
+/// Parses the number directly following `prefix` in a raw log, for example
+/// `account sequence mismatch, expected 10, got 9: incorrect account sequence`
+fn number_after(raw_log: &str, prefix: &str) -> Option<u64> {
+    let start = raw_log.find(prefix)? + prefix.len();
+    let digits: String = raw_log[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+impl Display for CosmosSdkError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            CosmosSdkError::InvalidSequence { expected, got } => {
+                write!(f, "Invalid sequence, expected {:?} got {:?}", expected, got)
+            }
+            CosmosSdkError::Unauthorized => write!(f, "Unauthorized"),
+            CosmosSdkError::InsufficientFunds => write!(f, "Insufficient funds"),
+            CosmosSdkError::InvalidAddress => write!(f, "Invalid address"),
+            CosmosSdkError::UnknownAddress => write!(f, "Unknown address"),
+            CosmosSdkError::OutOfGas { wanted, used } => {
+                write!(f, "Out of gas, wanted {:?} used {:?}", wanted, used)
+            }
+            CosmosSdkError::InsufficientFee => write!(f, "Insufficient fee"),
+            CosmosSdkError::TxInMempoolCache => write!(f, "Tx already in mempool"),
+            CosmosSdkError::MempoolIsFull => write!(f, "Mempool is full"),
+            CosmosSdkError::Other { codespace, code } => {
+                write!(f, "Error code {} in codespace {}", code, codespace)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_sdk_errors() {
+        let log = "account sequence mismatch, expected 10, got 9: incorrect account sequence";
+        assert_eq!(
+            CosmosSdkError::new("sdk", 3, log, None),
+            CosmosSdkError::InvalidSequence {
+                expected: Some(10),
+                got: Some(9)
+            }
+        );
+
+        let log =
+            "out of gas in location: WriteFlat; gasWanted: 200000, gasUsed: 200437: out of gas";
+        assert_eq!(
+            CosmosSdkError::new("sdk", 11, log, Some(5)),
+            CosmosSdkError::OutOfGas {
+                wanted: Some(200000),
+                used: Some(200437)
+            }
+        );
+        assert_eq!(
+            CosmosSdkError::new("sdk", 11, "out of gas", Some(5)),
+            CosmosSdkError::OutOfGas {
+                wanted: None,
+                used: Some(5)
+            }
+        );
+
+        assert_eq!(
+            CosmosSdkError::new("sdk", 5, "", None),
+            CosmosSdkError::InsufficientFunds
+        );
+        // module errors reuse the same codes in their own codespace
+        assert_eq!(
+            CosmosSdkError::new("bank", 5, "", None),
+            CosmosSdkError::Other {
+                codespace: "bank".to_string(),
+                code: 5
+            }
+        );
+    }
+}
@@ -1,15 +1,68 @@
-use actix_web::client::SendRequestError as ActixError;
+use crate::jsonrpc::cosmos_error::CosmosSdkError;
+use crate::types::TxSendErrorResponse;
 use std::error::Error;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fmt::Result;
+use std::time::Duration;
+
+/// Errors produced by a Transport, these are independent of the HTTP
+/// library actually in use so that callers can match on them
+#[derive(Debug, Clone)]
+pub enum TransportError {
+    /// Failed to connect to the server
+    Connect(String),
+    /// The connection was dropped before we got a response
+    Disconnected,
+    /// The request did not complete within the provided timeout
+    Timeout,
+    /// The response body was larger than the provided limit
+    Overflow,
+    /// Failed to read the response body
+    Body(String),
+    Other(String),
+}
+
+impl Display for TransportError {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match self {
+            TransportError::Connect(val) => write!(f, "Failed to connect {}", val),
+            TransportError::Disconnected => write!(f, "Connection was disconnected"),
+            TransportError::Timeout => write!(f, "Timeout while waiting for response"),
+            TransportError::Overflow => write!(f, "Response reached size limit"),
+            TransportError::Body(val) => write!(f, "Failed to read response body {}", val),
+            TransportError::Other(val) => write!(f, "{}", val),
+        }
+    }
+}
+
+impl Error for TransportError {}
 
 #[derive(Debug)]
 pub enum JsonRpcError {
     NoToken,
     BadResponse(String),
     BadStruct(String),
-    FailedToSend(ActixError),
+    FailedToSend(TransportError),
+    /// The node responded with a non success status code
+    HttpError {
+        status: u16,
+        /// the raw response body
+        body: String,
+        /// the Cosmos SDK error contained in the body, if it had one
+        cosmos_error: Option<TxSendErrorResponse>,
+    },
+    /// The node is rate limiting us, it responded with a 429 status
+    RateLimited {
+        /// how long the node asked us to wait before trying again
+        retry_after: Option<Duration>,
+        body: String,
+    },
+    /// The node rejected the request with a Cosmos SDK error
+    SdkError {
+        error: CosmosSdkError,
+        response: Box<TxSendErrorResponse>,
+    },
     ResponseError {
         code: i64,
         message: String,
@@ -17,6 +70,169 @@ pub enum JsonRpcError {
     },
     BadInput(String),
     ChainNotRunning,
+    /// The node is still catching up to the rest of the chain
+    NodeSyncing,
+    /// The node is on a different chain than the one we expected
+    WrongChain {
+        expected: String,
+        actual: String,
+    },
+    /// The node's Cosmos SDK version does not serve this endpoint, see Capabilities
+    Unsupported {
+        method: String,
+        /// the Cosmos SDK version the node reported
+        sdk_version: String,
+    },
+    /// The deadline for an operation passed before it could complete
+    DeadlineExceeded,
+    /// The node did not reach the state a wait_for helper was waiting for in time
+    WaitTimeout {
+        /// what was being waited for, for example "height 100"
+        condition: String,
+        /// the latest height the node reported, if it reported one
+        last_height: Option<u64>,
+        /// the error from the last poll, if it failed
+        last_error: Option<Box<JsonRpcError>>,
+    },
+    /// The response body was larger than the configured limit, see ResponseLimits
+    ResponseTooLarge {
+        limit: usize,
+        /// the url the request was sent to
+        endpoint: String,
+    },
+    /// A query pinned to a height was answered at a different height
+    HeightMismatch {
+        requested: u64,
+        returned: u64,
+    },
+    /// The node does not have the requested height, either because it was
+    /// pruned or because the chain has not reached it yet
+    HeightNotAvailable {
+        height: u64,
+        /// the lowest height the node still has, if it told us
+        lowest_available: Option<u64>,
+    },
+    /// Not enough nodes returned the same result for a quorum read
+    QuorumNotReached {
+        required: usize,
+        agreeing: usize,
+        /// urls of the nodes that returned a different result or failed
+        disagreeing: Vec<String>,
+    },
+}
+
+impl JsonRpcError {
+    /// Whether this error is likely to be temporary, meaning the same request
+    /// may succeed if it is tried again. Errors where the node has answered
+    /// and rejected the request are never considered retryable.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            JsonRpcError::FailedToSend(e) => matches!(
+                e,
+                TransportError::Connect(_) | TransportError::Disconnected | TransportError::Timeout
+            ),
+            JsonRpcError::BadResponse(_) | JsonRpcError::RateLimited { .. } => true,
+            // a route the node doesn't implement won't appear on a retry
+            JsonRpcError::HttpError { status, .. } => {
+                (*status >= 500 && *status != 501) || *status == 408
+            }
+            JsonRpcError::SdkError { error, .. } => *error == CosmosSdkError::MempoolIsFull,
+            _ => false,
+        }
+    }
+
+    /// The name of this error variant, used to label metrics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            JsonRpcError::NoToken => "NoToken",
+            JsonRpcError::BadResponse(_) => "BadResponse",
+            JsonRpcError::BadStruct(_) => "BadStruct",
+            JsonRpcError::FailedToSend(_) => "FailedToSend",
+            JsonRpcError::HttpError { .. } => "HttpError",
+            JsonRpcError::RateLimited { .. } => "RateLimited",
+            JsonRpcError::SdkError { .. } => "SdkError",
+            JsonRpcError::ResponseError { .. } => "ResponseError",
+            JsonRpcError::BadInput(_) => "BadInput",
+            JsonRpcError::ChainNotRunning => "ChainNotRunning",
+            JsonRpcError::NodeSyncing => "NodeSyncing",
+            JsonRpcError::WrongChain { .. } => "WrongChain",
+            JsonRpcError::Unsupported { .. } => "Unsupported",
+            JsonRpcError::DeadlineExceeded => "DeadlineExceeded",
+            JsonRpcError::WaitTimeout { .. } => "WaitTimeout",
+            JsonRpcError::ResponseTooLarge { .. } => "ResponseTooLarge",
+            JsonRpcError::HeightMismatch { .. } => "HeightMismatch",
+            JsonRpcError::HeightNotAvailable { .. } => "HeightNotAvailable",
+            JsonRpcError::QuorumNotReached { .. } => "QuorumNotReached",
+        }
+    }
+
+    /// The HTTP status code returned by the node, if it returned an error status
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            JsonRpcError::HttpError { status, .. } => Some(*status),
+            JsonRpcError::RateLimited { .. } => Some(429),
+            _ => None,
+        }
+    }
+
+    /// How long the node asked us to wait before sending another request
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            JsonRpcError::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// The typed Cosmos SDK error the node responded with, if any
+    pub fn sdk_error(&self) -> Option<CosmosSdkError> {
+        match self {
+            JsonRpcError::SdkError { error, .. } => Some(error.clone()),
+            JsonRpcError::HttpError {
+                cosmos_error: Some(response),
+                ..
+            } => Some(response.into()),
+            _ => None,
+        }
+    }
+
+    /// Converts the errors nodes return for heights they don't have into
+    /// HeightNotAvailable, any other error is returned unchanged
+    pub(crate) fn for_height(self, height: u64) -> JsonRpcError {
+        let body = match &self {
+            JsonRpcError::HttpError { body, .. } => body,
+            _ => return self,
+        };
+        // Tendermint phrases this differently depending on the version
+        let markers = [
+            "is not available",
+            "lowest height is",
+            "bigger then the chain length",
+            "must be less than or equal to the current blockchain height",
+        ];
+        if !markers.iter().any(|marker| body.contains(marker)) {
+            return self;
+        }
+        let lowest_available = body.split("lowest height is ").nth(1).and_then(|rest| {
+            let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse().ok()
+        });
+        JsonRpcError::HeightNotAvailable {
+            height,
+            lowest_available,
+        }
+    }
+
+    /// Whether this error means the node itself is unreachable or broken, as
+    /// opposed to the node working correctly and rejecting our request
+    pub fn is_node_failure(&self) -> bool {
+        match self {
+            JsonRpcError::FailedToSend(_)
+            | JsonRpcError::BadResponse(_)
+            | JsonRpcError::RateLimited { .. } => true,
+            JsonRpcError::HttpError { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
 }
 
 impl Display for JsonRpcError {
@@ -26,9 +242,29 @@ impl Display for JsonRpcError {
                 write!(f, "Account has no tokens! No details!")
             }
             JsonRpcError::BadResponse(val) => write!(f, "JsonRPC bad response {}", val),
+            JsonRpcError::SdkError { error, response } => {
+                write!(f, "JsonRPC Cosmos SDK error {} {}", error, response.raw_log)
+            }
             JsonRpcError::BadStruct(val) => write!(f, "JsonRPC unexpected json returned {}", val),
             JsonRpcError::BadInput(val) => write!(f, "JsonRPC bad input {}", val),
             JsonRpcError::FailedToSend(val) => write!(f, "JsonRPC Failed to send {}", val),
+            JsonRpcError::HttpError {
+                status,
+                body,
+                cosmos_error,
+            } => match cosmos_error {
+                Some(e) => write!(
+                    f,
+                    "JsonRPC server error {} code {} codespace {} {}",
+                    status, e.code, e.codespace, e.raw_log
+                ),
+                None => write!(f, "JsonRPC server error {} {}", status, body),
+            },
+            JsonRpcError::RateLimited { retry_after, body } => write!(
+                f,
+                "JsonRPC rate limited, retry after {:?} {}",
+                retry_after, body
+            ),
             JsonRpcError::ResponseError {
                 code,
                 message,
@@ -41,6 +277,70 @@ impl Display for JsonRpcError {
             JsonRpcError::ChainNotRunning => {
                 write!(f, "JsonRPC this node is waiting on a blockchain start")
             }
+            JsonRpcError::NodeSyncing => write!(f, "JsonRPC this node is still syncing"),
+            JsonRpcError::WrongChain { expected, actual } => write!(
+                f,
+                "JsonRPC expected a node on chain {} but this node is on {}",
+                expected, actual
+            ),
+            JsonRpcError::Unsupported {
+                method,
+                sdk_version,
+            } => write!(
+                f,
+                "JsonRPC {} is not supported by Cosmos SDK {} nodes",
+                method, sdk_version
+            ),
+            JsonRpcError::DeadlineExceeded => {
+                write!(f, "JsonRPC deadline passed before the operation completed")
+            }
+            JsonRpcError::WaitTimeout {
+                condition,
+                last_height,
+                last_error,
+            } => {
+                write!(f, "JsonRPC timed out waiting for {}", condition)?;
+                if let Some(height) = last_height {
+                    write!(f, ", latest height {}", height)?;
+                }
+                match last_error {
+                    Some(e) => write!(f, ", last error {}", e),
+                    None => Ok(()),
+                }
+            }
+            JsonRpcError::ResponseTooLarge { limit, endpoint } => write!(
+                f,
+                "JsonRPC response from {} is larger than the limit of {} bytes",
+                endpoint, limit
+            ),
+            JsonRpcError::HeightMismatch {
+                requested,
+                returned,
+            } => write!(
+                f,
+                "JsonRPC requested state at height {} but got height {}",
+                requested, returned
+            ),
+            JsonRpcError::HeightNotAvailable {
+                height,
+                lowest_available,
+            } => match lowest_available {
+                Some(lowest) => write!(
+                    f,
+                    "JsonRPC height {} is not available, the lowest available height is {}",
+                    height, lowest
+                ),
+                None => write!(f, "JsonRPC height {} is not available", height),
+            },
+            JsonRpcError::QuorumNotReached {
+                required,
+                agreeing,
+                disagreeing,
+            } => write!(
+                f,
+                "JsonRPC quorum not reached, {} of {} required nodes agreed, disagreeing nodes {:?}",
+                agreeing, required, disagreeing
+            ),
         }
     }
 }
@@ -0,0 +1,284 @@
+use crate::jsonrpc::error::{JsonRpcError, TransportError};
+use crate::jsonrpc::transport::{parse_retry_after, Transport, TransportConfig, TransportResponse};
+use crate::utils::{basic_auth_header, split_credentials};
+use async_trait::async_trait;
+use hyper::body::HttpBody;
+use hyper::client::HttpConnector;
+use hyper::header::{
+    HeaderName, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE, PROXY_AUTHORIZATION, RETRY_AFTER,
+};
+use hyper::{Body, Client, Method, Request};
+use hyper_proxy::{Intercept, Proxy, ProxyConnector};
+use hyper_tls::HttpsConnector;
+use native_tls::{Certificate, Identity, TlsConnector};
+use std::time::Duration;
+use tokio::time::timeout as with_timeout;
+
+type Connector = ProxyConnector<HttpsConnector<HttpConnector>>;
+
+/// A Transport backed by hyper, unlike the actix transport this does not
+/// need an actix System and can be used from any tokio runtime
+#[derive(Clone)]
+pub struct HyperTransport {
+    client: Client<Connector>,
+    /// plain http requests sent through a proxy carry the proxy credentials
+    /// themselves, https requests send them when opening the tunnel
+    proxy_authorization: Option<String>,
+}
+
+impl Default for HyperTransport {
+    fn default() -> Self {
+        HyperTransport::from_config(&TransportConfig::default())
+            .expect("Default hyper configuration is invalid!")
+    }
+}
+
+impl HyperTransport {
+    pub fn new(client: Client<Connector>) -> Self {
+        HyperTransport {
+            client,
+            proxy_authorization: None,
+        }
+    }
+
+    /// Creates a hyper client with the provided settings
+    pub fn from_config(config: &TransportConfig) -> Result<Self, JsonRpcError> {
+        let tls = tls_connector(config)
+            .map_err(|e| JsonRpcError::BadInput(format!("Invalid TLS configuration {}", e)))?;
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+        let https = HttpsConnector::from((http, tls.clone().into()));
+
+        let mut proxy_authorization = None;
+        let connector = match &config.proxy {
+            Some(url) => {
+                let (url, credentials) = split_credentials(url);
+                let uri = url
+                    .parse()
+                    .map_err(|e| JsonRpcError::BadInput(format!("Invalid proxy url {}", e)))?;
+                let mut proxy = Proxy::new(Intercept::All, uri);
+                if let Some((user, password)) = credentials {
+                    let auth = basic_auth_header(&user, password.as_deref());
+                    let value = HeaderValue::from_str(&auth).map_err(|e| {
+                        JsonRpcError::BadInput(format!("Invalid proxy credentials {}", e))
+                    })?;
+                    proxy.set_header(PROXY_AUTHORIZATION, value);
+                    proxy_authorization = Some(auth);
+                }
+                let mut connector = ProxyConnector::from_proxy_unsecured(https, proxy);
+                connector.set_tls(Some(tls));
+                connector
+            }
+            None => ProxyConnector::unsecured(https),
+        };
+
+        let mut builder = Client::builder();
+        if let Some(max) = config.max_connections {
+            builder.pool_max_idle_per_host(max);
+        }
+        if let Some(idle_timeout) = config.idle_timeout {
+            builder.pool_idle_timeout(idle_timeout);
+        }
+        Ok(HyperTransport {
+            client: builder.build(connector),
+            proxy_authorization,
+        })
+    }
+
+    fn build_request(
+        &self,
+        method: Method,
+        url: &str,
+        headers: &[(String, String)],
+        body: Body,
+    ) -> Result<Request<Body>, TransportError> {
+        let mut request = Request::builder().method(method).uri(url).body(body)?;
+        let request_headers = request.headers_mut();
+        request_headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        // insert rather than append, a header set twice is sent once with the
+        // last value like the actix transport does
+        for (key, value) in headers {
+            let key = HeaderName::from_bytes(key.as_bytes()).map_err(hyper::http::Error::from)?;
+            let value = HeaderValue::from_str(value).map_err(hyper::http::Error::from)?;
+            request_headers.insert(key, value);
+        }
+        if let Some(auth) = &self.proxy_authorization {
+            if url.starts_with("http:") {
+                let value = HeaderValue::from_str(auth).map_err(hyper::http::Error::from)?;
+                request_headers.insert(PROXY_AUTHORIZATION, value);
+            }
+        }
+        Ok(request)
+    }
+
+    async fn request(
+        &self,
+        request: Request<Body>,
+        limit: usize,
+    ) -> Result<TransportResponse, TransportError> {
+        let res = self.client.request(request).await?;
+        let status = res.status().as_u16();
+        let retry_after = res
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_retry_after);
+        if let Some(len) = res.headers().get(CONTENT_LENGTH) {
+            if let Some(len) = len.to_str().ok().and_then(|l| l.parse::<usize>().ok()) {
+                if len > limit {
+                    return Err(TransportError::Overflow);
+                }
+            }
+        }
+
+        // read the body one chunk at a time so that we can bail out as soon
+        // as the limit is passed rather than reading all of an oversized response
+        let mut body = res.into_body();
+        let mut buf = Vec::new();
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk.map_err(|e| TransportError::Body(e.to_string()))?;
+            if buf.len() + chunk.len() > limit {
+                return Err(TransportError::Overflow);
+            }
+            buf.extend_from_slice(&chunk);
+        }
+        Ok(TransportResponse {
+            status,
+            body: buf,
+            retry_after,
+        })
+    }
+}
+
+#[cfg_attr(feature = "actix", async_trait(?Send))]
+#[cfg_attr(not(feature = "actix"), async_trait)]
+impl Transport for HyperTransport {
+    async fn get(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        timeout: Duration,
+        limit: usize,
+    ) -> Result<TransportResponse, TransportError> {
+        let request = self.build_request(Method::GET, url, headers, Body::empty())?;
+        match with_timeout(timeout, self.request(request, limit)).await {
+            Ok(res) => res,
+            Err(_) => Err(TransportError::Timeout),
+        }
+    }
+
+    async fn post_json(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        body: Vec<u8>,
+        timeout: Duration,
+        limit: usize,
+    ) -> Result<TransportResponse, TransportError> {
+        let request = self.build_request(Method::POST, url, headers, Body::from(body))?;
+        match with_timeout(timeout, self.request(request, limit)).await {
+            Ok(res) => res,
+            Err(_) => Err(TransportError::Timeout),
+        }
+    }
+}
+
+impl From<hyper::Error> for TransportError {
+    fn from(error: hyper::Error) -> Self {
+        if error.is_connect() {
+            TransportError::Connect(error.to_string())
+        } else if error.is_closed() || error.is_canceled() || error.is_incomplete_message() {
+            TransportError::Disconnected
+        } else {
+            TransportError::Other(error.to_string())
+        }
+    }
+}
+
+fn tls_connector(config: &TransportConfig) -> Result<TlsConnector, native_tls::Error> {
+    let mut builder = TlsConnector::builder();
+    if let Some(root) = &config.root_certificate {
+        builder.add_root_certificate(Certificate::from_pem(root)?);
+    }
+    if let Some(cert) = &config.client_certificate {
+        builder.identity(Identity::from_pkcs8(&cert.certificate, &cert.private_key)?);
+    }
+    builder.build()
+}
+
+impl From<hyper::http::Error> for TransportError {
+    fn from(error: hyper::http::Error) -> Self {
+        TransportError::Other(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonrpc::error::JsonRpcError;
+    use crate::jsonrpc::transport::mock::test_contact;
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Response, Server, StatusCode};
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+
+    /// starts a server on a random local port that answers `syncing` and
+    /// responds with an error to everything else
+    fn start_server() -> SocketAddr {
+        let make_svc = make_service_fn(|_conn| async {
+            Ok::<_, Infallible>(service_fn(|req: Request<Body>| async move {
+                let res = if req.uri().path() == "/syncing" {
+                    Response::new(Body::from(r#"{"syncing":false}"#))
+                } else {
+                    let mut res = Response::new(Body::empty());
+                    *res.status_mut() = StatusCode::NOT_FOUND;
+                    res
+                };
+                Ok::<_, Infallible>(res)
+            }))
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_svc);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_hyper_transport() {
+        let addr = start_server();
+        let url = format!("http://{}", addr);
+        let contact = test_contact(&[&url], HyperTransport::default());
+
+        let res = contact.get_syncing_status().await.unwrap();
+        assert!(!res.syncing);
+
+        match contact.get_tx_by_hash("ABCD").await {
+            Err(JsonRpcError::HttpError { status: 404, .. }) => {}
+            res => panic!("Unexpected result {:?}", res),
+        }
+
+        let contact = test_contact(&["http://127.0.0.1:1"], HyperTransport::default());
+        match contact.get_syncing_status().await {
+            Err(JsonRpcError::FailedToSend(TransportError::Connect(_))) => {}
+            res => panic!("Unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_headers_replaced() {
+        let transport = HyperTransport::default();
+        let headers = vec![
+            (
+                "Authorization".to_string(),
+                "Basic dXNlcjpwYXNz".to_string(),
+            ),
+            ("Authorization".to_string(), "Bearer token".to_string()),
+        ];
+        let request = transport
+            .build_request(Method::GET, "http://node/syncing", &headers, Body::empty())
+            .unwrap();
+        let values: Vec<_> = request.headers().get_all("authorization").iter().collect();
+        assert_eq!(values, vec!["Bearer token"]);
+        assert_eq!(request.headers()[CONTENT_TYPE], "application/json");
+    }
+}
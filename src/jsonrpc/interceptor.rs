@@ -0,0 +1,176 @@
+use crate::jsonrpc::error::JsonRpcError;
+use crate::jsonrpc::transport::MaybeSendSync;
+use serde_json::Value;
+#[cfg(feature = "actix")]
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A request about to be sent by HTTPClient, interceptors may modify any of it
+#[derive(Debug, Clone)]
+pub struct InterceptedRequest {
+    /// the full url the request will be sent to
+    pub url: String,
+    /// the method path relative to the endpoint, for example `blocks/latest`
+    pub method: String,
+    pub headers: Vec<(String, String)>,
+    /// the json payload, None for GET requests
+    pub body: Option<Vec<u8>>,
+}
+
+/// The outcome of an intercepted request, before it is decoded into the type
+/// the caller asked for
+#[derive(Debug)]
+pub struct InterceptedResponse {
+    /// the status code, None if no response was received or the request
+    /// was short circuited
+    pub status: Option<u16>,
+    pub latency: Duration,
+    /// the decoded body, interceptors may replace this to rewrite the response
+    pub result: Result<Value, JsonRpcError>,
+}
+
+/// A hook into every request HTTPClient makes, for things like signing
+/// requests, audit logging, working around buggy nodes, or injecting faults
+/// in tests. Interceptors run in the order they were added.
+pub trait Interceptor: MaybeSendSync {
+    /// Called before a request is sent. Returning Some skips the request and
+    /// every later interceptor, using the returned result as the response.
+    /// Skipped requests don't count towards endpoint health or metrics
+    fn before_request(
+        &self,
+        _request: &mut InterceptedRequest,
+    ) -> Option<Result<Value, JsonRpcError>> {
+        None
+    }
+
+    /// Called with every response, including short circuited ones, before it
+    /// is decoded
+    fn after_response(&self, _request: &InterceptedRequest, _response: &mut InterceptedResponse) {}
+}
+
+/// Allows a caller to keep a handle to an interceptor after handing it to Contact
+impl<T: Interceptor + ?Sized> Interceptor for Arc<T> {
+    fn before_request(
+        &self,
+        request: &mut InterceptedRequest,
+    ) -> Option<Result<Value, JsonRpcError>> {
+        (**self).before_request(request)
+    }
+
+    fn after_response(&self, request: &InterceptedRequest, response: &mut InterceptedResponse) {
+        (**self).after_response(request, response)
+    }
+}
+
+#[cfg(feature = "actix")]
+impl<T: Interceptor + ?Sized> Interceptor for Rc<T> {
+    fn before_request(
+        &self,
+        request: &mut InterceptedRequest,
+    ) -> Option<Result<Value, JsonRpcError>> {
+        (**self).before_request(request)
+    }
+
+    fn after_response(&self, request: &InterceptedRequest, response: &mut InterceptedResponse) {
+        (**self).after_response(request, response)
+    }
+}
+
+impl<T: Interceptor + ?Sized> Interceptor for Box<T> {
+    fn before_request(
+        &self,
+        request: &mut InterceptedRequest,
+    ) -> Option<Result<Value, JsonRpcError>> {
+        (**self).before_request(request)
+    }
+
+    fn after_response(&self, request: &InterceptedRequest, response: &mut InterceptedResponse) {
+        (**self).after_response(request, response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ContactBuilder;
+    use crate::jsonrpc::error::TransportError;
+    use crate::jsonrpc::transport::mock::MockTransport;
+    use actix::System;
+    use std::sync::Mutex;
+
+    /// Signs requests, fakes syncing status and records what it saw
+    #[derive(Default)]
+    struct TestInterceptor {
+        seen: Mutex<Vec<(String, Option<u16>)>>,
+    }
+
+    impl Interceptor for TestInterceptor {
+        fn before_request(
+            &self,
+            request: &mut InterceptedRequest,
+        ) -> Option<Result<Value, JsonRpcError>> {
+            request
+                .headers
+                .push(("X-Signature".to_string(), request.method.clone()));
+            if request.method == "blocks/latest" {
+                return Some(Err(JsonRpcError::FailedToSend(TransportError::Timeout)));
+            }
+            None
+        }
+
+        fn after_response(&self, request: &InterceptedRequest, response: &mut InterceptedResponse) {
+            self.seen
+                .lock()
+                .unwrap()
+                .push((request.method.clone(), response.status));
+            if let Ok(value) = &mut response.result {
+                value["syncing"] = Value::Bool(false);
+            }
+        }
+    }
+
+    #[test]
+    fn test_interceptors() {
+        let transport = Arc::new(MockTransport::default());
+        transport.respond("http://a/syncing", 200, r#"{"syncing":true}"#);
+        let interceptor = Arc::new(TestInterceptor::default());
+        let contact = ContactBuilder::new("http://a", Duration::from_secs(1))
+            .transport(transport.clone())
+            .retry_policy(crate::client::RetryPolicy::never())
+            .interceptor(interceptor.clone())
+            .build()
+            .unwrap();
+
+        System::new("test").block_on(async move {
+            // the response was rewritten
+            assert!(!contact.get_syncing_status().await.unwrap().syncing);
+            match contact.get_latest_block().await {
+                Err(JsonRpcError::FailedToSend(TransportError::Timeout)) => {}
+                res => panic!("Unexpected result {:?}", res),
+            }
+
+            // the short circuited request is not held against the node
+            let health = &contact.jsonrpc_client.endpoints()[0].1;
+            assert_eq!((health.successes, health.failures), (1, 0));
+            assert_eq!(contact.jsonrpc_client.metrics().requests("http://a"), 1);
+        });
+
+        // the short circuited request never reached the transport
+        assert_eq!(
+            *transport.requests.lock().unwrap(),
+            vec!["http://a/syncing"]
+        );
+        assert_eq!(
+            transport.headers.lock().unwrap()[0],
+            vec![("X-Signature".to_string(), "syncing".to_string())]
+        );
+        assert_eq!(
+            *interceptor.seen.lock().unwrap(),
+            vec![
+                ("syncing".to_string(), Some(200)),
+                ("blocks/latest".to_string(), None)
+            ]
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use crate::jsonrpc::error::JsonRpcError;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds in seconds of the request latency histogram buckets
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Request metrics collected by HTTPClient, render these with to_prometheus
+/// and serve them from whatever http server the application already has
+#[derive(Debug, Default)]
+pub struct Metrics {
+    inner: Mutex<MetricsInner>,
+}
+
+#[derive(Debug, Default)]
+struct MetricsInner {
+    /// (endpoint, method) -> requests sent
+    requests: BTreeMap<(String, String), u64>,
+    /// endpoint -> latency histogram
+    latency: BTreeMap<String, Histogram>,
+    /// (endpoint, error kind) -> errors
+    errors: BTreeMap<(String, &'static str), u64>,
+    /// method -> retries
+    retries: BTreeMap<String, u64>,
+    /// endpoint -> response bytes received
+    bytes: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    /// non cumulative counts for each of LATENCY_BUCKETS, the last entry
+    /// counts samples larger than every bucket
+    buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| value <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[bucket] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+impl Metrics {
+    /// Records a completed request, `method` is normalized so that requests for
+    /// different addresses, hashes or heights are counted together
+    pub fn record_request(
+        &self,
+        endpoint: &str,
+        method: &str,
+        latency: Duration,
+        res: Result<(), &JsonRpcError>,
+    ) {
+        let mut inner = self.inner.lock().unwrap();
+        *inner
+            .requests
+            .entry((endpoint.to_string(), normalize_method(method)))
+            .or_default() += 1;
+        inner
+            .latency
+            .entry(endpoint.to_string())
+            .or_default()
+            .observe(latency.as_secs_f64());
+        if let Err(e) = res {
+            *inner
+                .errors
+                .entry((endpoint.to_string(), e.kind()))
+                .or_default() += 1;
+        }
+    }
+
+    pub fn record_retries(&self, method: &str, retries: u64) {
+        if retries > 0 {
+            let mut inner = self.inner.lock().unwrap();
+            *inner.retries.entry(normalize_method(method)).or_default() += retries;
+        }
+    }
+
+    pub fn record_bytes(&self, endpoint: &str, bytes: usize) {
+        let mut inner = self.inner.lock().unwrap();
+        *inner.bytes.entry(endpoint.to_string()).or_default() += bytes as u64;
+    }
+
+    /// The number of requests sent to an endpoint for all methods
+    pub fn requests(&self, endpoint: &str) -> u64 {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .requests
+            .iter()
+            .filter(|((e, _), _)| e == endpoint)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    /// Renders every metric in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let inner = self.inner.lock().unwrap();
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "contact_requests_total",
+            "counter",
+            "Requests sent to each endpoint",
+        );
+        for ((endpoint, method), count) in inner.requests.iter() {
+            let _ = writeln!(
+                out,
+                "contact_requests_total{{endpoint=\"{}\",method=\"{}\"}} {}",
+                escape(endpoint),
+                escape(method),
+                count
+            );
+        }
+
+        header(
+            &mut out,
+            "contact_request_duration_seconds",
+            "histogram",
+            "Request latency for each endpoint",
+        );
+        for (endpoint, histogram) in inner.latency.iter() {
+            let endpoint = escape(endpoint);
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets.iter()) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "contact_request_duration_seconds_bucket{{endpoint=\"{}\",le=\"{}\"}} {}",
+                    endpoint, bound, cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "contact_request_duration_seconds_bucket{{endpoint=\"{}\",le=\"+Inf\"}} {}",
+                endpoint, histogram.count
+            );
+            let _ = writeln!(
+                out,
+                "contact_request_duration_seconds_sum{{endpoint=\"{}\"}} {}",
+                endpoint, histogram.sum
+            );
+            let _ = writeln!(
+                out,
+                "contact_request_duration_seconds_count{{endpoint=\"{}\"}} {}",
+                endpoint, histogram.count
+            );
+        }
+
+        header(
+            &mut out,
+            "contact_errors_total",
+            "counter",
+            "Failed requests by endpoint and error",
+        );
+        for ((endpoint, kind), count) in inner.errors.iter() {
+            let _ = writeln!(
+                out,
+                "contact_errors_total{{endpoint=\"{}\",error=\"{}\"}} {}",
+                escape(endpoint),
+                kind,
+                count
+            );
+        }
+
+        header(
+            &mut out,
+            "contact_retries_total",
+            "counter",
+            "Requests retried by the retry policy",
+        );
+        for (method, count) in inner.retries.iter() {
+            let _ = writeln!(
+                out,
+                "contact_retries_total{{method=\"{}\"}} {}",
+                escape(method),
+                count
+            );
+        }
+
+        header(
+            &mut out,
+            "contact_response_bytes_total",
+            "counter",
+            "Response body bytes received from each endpoint",
+        );
+        for (endpoint, bytes) in inner.bytes.iter() {
+            let _ = writeln!(
+                out,
+                "contact_response_bytes_total{{endpoint=\"{}\"}} {}",
+                escape(endpoint),
+                bytes
+            );
+        }
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, metric_type: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, metric_type);
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Replaces the parts of a method path that identify a specific address, hash
+/// or height so that the number of label values stays small
+fn normalize_method(method: &str) -> String {
+    let path = method.split('?').next().unwrap_or_default();
+    path.split('/')
+        .map(|segment| {
+            if !segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit()) {
+                "{height}"
+            } else if segment.len() >= 20 && segment.chars().any(|c| c.is_ascii_digit()) {
+                "{id}"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonrpc::error::TransportError;
+
+    #[test]
+    fn test_prometheus_format() {
+        assert_eq!(
+            normalize_method("bank/balances/cosmos1qnk2n4nlkpw9xfqntladh74w6ujtulwnmxnh3k"),
+            "bank/balances/{id}"
+        );
+        assert_eq!(normalize_method("blocks/100?height=5"), "blocks/{height}");
+        assert_eq!(normalize_method("blocks/latest"), "blocks/latest");
+
+        let metrics = Metrics::default();
+        metrics.record_request("http://a", "syncing", Duration::from_millis(20), Ok(()));
+        metrics.record_request(
+            "http://a",
+            "syncing",
+            Duration::from_secs(20),
+            Err(&JsonRpcError::FailedToSend(TransportError::Timeout)),
+        );
+        metrics.record_retries("txs", 2);
+        metrics.record_bytes("http://a", 100);
+        assert_eq!(metrics.requests("http://a"), 2);
+
+        let text = metrics.to_prometheus();
+        for line in &[
+            "# TYPE contact_requests_total counter",
+            "contact_requests_total{endpoint=\"http://a\",method=\"syncing\"} 2",
+            "contact_request_duration_seconds_bucket{endpoint=\"http://a\",le=\"0.01\"} 0",
+            "contact_request_duration_seconds_bucket{endpoint=\"http://a\",le=\"0.025\"} 1",
+            "contact_request_duration_seconds_bucket{endpoint=\"http://a\",le=\"10\"} 1",
+            "contact_request_duration_seconds_bucket{endpoint=\"http://a\",le=\"+Inf\"} 2",
+            "contact_request_duration_seconds_count{endpoint=\"http://a\"} 2",
+            "contact_errors_total{endpoint=\"http://a\",error=\"FailedToSend\"} 1",
+            "contact_retries_total{method=\"txs\"} 2",
+            "contact_response_bytes_total{endpoint=\"http://a\"} 100",
+        ] {
+            assert!(text.lines().any(|l| l == *line), "missing {}", line);
+        }
+    }
+}
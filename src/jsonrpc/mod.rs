@@ -1,2 +1,13 @@
+#[cfg(feature = "actix")]
+pub mod actix_transport;
 pub mod client;
+pub mod cosmos_error;
+pub mod endpoint;
 pub mod error;
+#[cfg(feature = "hyper-transport")]
+pub mod hyper_transport;
+pub mod interceptor;
+pub mod metrics;
+pub mod quorum;
+pub mod rate_limit;
+pub mod transport;
@@ -0,0 +1,251 @@
+use crate::jsonrpc::client::{decode_value, HTTPClient};
+use crate::jsonrpc::endpoint::by_health;
+use crate::jsonrpc::error::JsonRpcError;
+use futures::future::join_all;
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+/// Configuration for quorum reads, where the same query is sent to several
+/// nodes and only a result that enough of them agree on is accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuorumConfig {
+    /// how many nodes to query, the healthiest nodes are used first
+    pub nodes: usize,
+    /// how many of the queried nodes must return an identical result
+    pub threshold: usize,
+}
+
+impl QuorumConfig {
+    /// Requires more than half of the queried nodes to agree
+    pub fn majority(nodes: usize) -> Self {
+        QuorumConfig {
+            nodes,
+            threshold: nodes / 2 + 1,
+        }
+    }
+}
+
+impl HTTPClient {
+    /// Sends the same GET request to several nodes and returns the result that
+    /// at least `quorum.threshold` of them agree on. Responses are compared at
+    /// the same height, nodes that answer at a newer height than the others
+    /// are asked again for the lowest height seen. Nodes known to be syncing
+    /// are not asked, so they never count toward the threshold.
+    pub async fn quorum_request_method<R>(
+        &self,
+        method: &str,
+        quorum: QuorumConfig,
+        timeout: Duration,
+        request_size_limit: Option<usize>,
+    ) -> Result<R, JsonRpcError>
+    where
+        for<'de> R: Deserialize<'de>,
+        R: std::fmt::Debug + 'static,
+    {
+        let start = Instant::now();
+        self.check_syncing(timeout).await;
+        let timeout = timeout
+            .checked_sub(start.elapsed())
+            .ok_or(JsonRpcError::DeadlineExceeded)?;
+        let none: Option<bool> = None;
+        let endpoints = self.endpoints_slice();
+        let nodes: Vec<usize> = by_health(endpoints)
+            .into_iter()
+            .filter(|i| !endpoints[*i].health().syncing)
+            .take(quorum.nodes)
+            .collect();
+        let requests = nodes.iter().map(|i| {
+            self.request_method_on::<_, Value>(*i, method, none, timeout, request_size_limit)
+        });
+        let mut results: Vec<Result<Value, JsonRpcError>> = join_all(requests).await;
+
+        // nodes will often be a block or two apart, if so ask the nodes that are
+        // ahead for the state at the lowest height any node returned
+        let heights: Vec<Option<u64>> = results
+            .iter()
+            .map(|res| res.as_ref().ok().and_then(response_height))
+            .collect();
+        if let Some(min) = heights.iter().flatten().min() {
+            let at_height = &with_height(method, *min);
+            let requests = nodes
+                .iter()
+                .zip(heights.iter())
+                .filter(|(_, height)| matches!(height, Some(h) if h != min))
+                .map(|(i, _)| async move {
+                    let res = self
+                        .request_method_on::<_, Value>(
+                            *i,
+                            at_height,
+                            none,
+                            timeout,
+                            request_size_limit,
+                        )
+                        .await;
+                    (*i, res)
+                });
+            for (i, res) in join_all(requests).await {
+                let pos = nodes.iter().position(|n| *n == i).unwrap();
+                results[pos] = res;
+            }
+        }
+
+        // find the largest group of identical responses
+        let mut best: Option<(&Value, usize)> = None;
+        for res in results.iter().flatten() {
+            let count = results
+                .iter()
+                .filter(|other| matches!(other, Ok(v) if v == res))
+                .count();
+            if !matches!(best, Some((_, c)) if c >= count) {
+                best = Some((res, count));
+            }
+        }
+
+        let endpoints = self.endpoints();
+        match best {
+            Some((value, agreeing)) if agreeing >= quorum.threshold => decode_value(value.clone()),
+            best => {
+                let disagreeing = nodes
+                    .iter()
+                    .zip(results.iter())
+                    .filter(|(_, res)| match (best, res) {
+                        (Some((value, _)), Ok(v)) => v != value,
+                        _ => true,
+                    })
+                    .map(|(i, _)| endpoints[*i].0.clone())
+                    .collect();
+                Err(JsonRpcError::QuorumNotReached {
+                    required: quorum.threshold,
+                    agreeing: best.map_or(0, |(_, c)| c),
+                    disagreeing,
+                })
+            }
+        }
+    }
+}
+
+/// The height a Cosmos REST response was produced at, if it has one
+pub(crate) fn response_height(value: &Value) -> Option<u64> {
+    value["height"].as_str()?.parse().ok()
+}
+
+/// Adds a height query parameter to a request path
+pub(crate) fn with_height(method: &str, height: u64) -> String {
+    if method.contains('?') {
+        format!("{}&height={}", method, height)
+    } else {
+        format!("{}?height={}", method, height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonrpc::transport::mock::MockTransport;
+    use crate::types::ResponseWrapper;
+    use actix::System;
+    use std::sync::Arc;
+
+    fn balance(height: u64, amount: u64) -> String {
+        format!(
+            r#"{{"height":"{}","result":[{{"denom":"footoken","amount":"{}"}}]}}"#,
+            height, amount
+        )
+    }
+
+    fn quorum_request(
+        transport: Arc<MockTransport>,
+        quorum: QuorumConfig,
+    ) -> Result<ResponseWrapper<Value>, JsonRpcError> {
+        let client = HTTPClient::with_endpoints(&["http://a", "http://b", "http://c"], transport);
+        System::new("test").block_on(async move {
+            client
+                .quorum_request_method("bank/balances/x", quorum, Duration::from_secs(1), None)
+                .await
+        })
+    }
+
+    #[test]
+    fn test_quorum() {
+        let transport = Arc::new(MockTransport::default());
+        transport.respond("http://a/bank/balances/x", 200, &balance(10, 5));
+        transport.respond("http://b/bank/balances/x", 200, &balance(10, 5));
+        transport.respond("http://c/bank/balances/x", 200, &balance(10, 6));
+
+        let res = quorum_request(transport.clone(), QuorumConfig::majority(3)).unwrap();
+        assert_eq!(res.result[0]["amount"], "5");
+
+        let quorum = QuorumConfig {
+            nodes: 3,
+            threshold: 3,
+        };
+        match quorum_request(transport, quorum) {
+            Err(JsonRpcError::QuorumNotReached {
+                required: 3,
+                agreeing: 2,
+                disagreeing,
+            }) => assert_eq!(disagreeing, vec!["http://c"]),
+            res => panic!("Unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_quorum_matches_heights() {
+        let transport = Arc::new(MockTransport::default());
+        transport.respond("http://a/bank/balances/x", 200, &balance(10, 5));
+        transport.respond("http://b/bank/balances/x", 200, &balance(11, 7));
+        transport.respond("http://b/bank/balances/x?height=10", 200, &balance(10, 5));
+        transport.respond("http://c/bank/balances/x", 200, &balance(10, 5));
+        let quorum = QuorumConfig {
+            nodes: 3,
+            threshold: 3,
+        };
+
+        let res = quorum_request(transport.clone(), quorum).unwrap();
+        assert_eq!(res.height, 10);
+        assert!(transport
+            .requests
+            .lock()
+            .unwrap()
+            .contains(&"http://b/bank/balances/x?height=10".to_string()));
+    }
+
+    #[test]
+    fn test_quorum_skips_syncing() {
+        let transport = Arc::new(MockTransport::default());
+        transport.respond("http://a/syncing", 200, r#"{"syncing":false}"#);
+        transport.respond("http://b/syncing", 200, r#"{"syncing":false}"#);
+        transport.respond("http://c/syncing", 200, r#"{"syncing":true}"#);
+        transport.respond("http://a/bank/balances/x", 200, &balance(10, 5));
+        transport.respond("http://b/bank/balances/x", 200, &balance(10, 6));
+        transport.respond("http://c/bank/balances/x", 200, &balance(10, 5));
+        let mut client =
+            HTTPClient::with_endpoints(&["http://a", "http://b", "http://c"], transport.clone());
+        client.set_health_check_interval(Some(Duration::from_secs(60)));
+
+        let res = System::new("test").block_on(async move {
+            client
+                .quorum_request_method::<ResponseWrapper<Value>>(
+                    "bank/balances/x",
+                    QuorumConfig::majority(3),
+                    Duration::from_secs(1),
+                    None,
+                )
+                .await
+        });
+        match res {
+            Err(JsonRpcError::QuorumNotReached {
+                required: 2,
+                agreeing: 1,
+                ..
+            }) => {}
+            res => panic!("Unexpected result {:?}", res),
+        }
+        assert!(!transport
+            .requests
+            .lock()
+            .unwrap()
+            .contains(&"http://c/bank/balances/x".to_string()));
+    }
+}
@@ -0,0 +1,222 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::time::delay_for;
+
+/// Limits on the requests HTTPClient sends to each endpoint, requests over a
+/// limit wait their turn in the order they were made rather than failing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// the maximum number of requests in flight to a single endpoint
+    pub max_in_flight: Option<usize>,
+    /// the sustained number of requests per second sent to a single endpoint
+    pub requests_per_second: Option<f64>,
+    /// how many requests may be sent at once before requests_per_second applies
+    pub burst: u32,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        RateLimit {
+            max_in_flight: None,
+            requests_per_second: None,
+            burst: 1,
+        }
+    }
+}
+
+/// Enforces a RateLimit for a single endpoint
+#[derive(Debug)]
+pub(crate) struct Limiter {
+    in_flight: Option<Semaphore>,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    rate: Option<f64>,
+    burst: f64,
+    /// negative when requests are queued waiting for tokens, each waiting
+    /// request has already taken the token it will use, which keeps the
+    /// queue in order
+    tokens: f64,
+    last: Instant,
+    /// set when the endpoint asked us to back off, see pause
+    paused_until: Option<Instant>,
+}
+
+impl Bucket {
+    /// Takes a token, returning how long the caller must wait before using it
+    fn reserve(&mut self, now: Instant) -> Duration {
+        let mut wait = Duration::from_secs(0);
+        if let Some(rate) = self.rate {
+            let elapsed = (now - self.last).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * rate).min(self.burst);
+            self.last = now;
+            self.tokens -= 1.0;
+            if self.tokens < 0.0 {
+                wait = Duration::from_secs_f64(-self.tokens / rate);
+            }
+        }
+        match self.paused_until {
+            Some(until) if until > now => wait.max(until - now),
+            _ => wait,
+        }
+    }
+
+    /// Gives back a token taken by reserve that was never used
+    fn refund(&mut self) {
+        if self.rate.is_some() {
+            self.tokens = (self.tokens + 1.0).min(self.burst);
+        }
+    }
+}
+
+/// A token taken from a Bucket, given back if the request stops waiting for
+/// it, by timing out or being dropped, so later requests don't wait for it
+struct Reservation<'a> {
+    bucket: &'a Mutex<Bucket>,
+    used: bool,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if !self.used {
+            self.bucket.lock().unwrap().refund();
+        }
+    }
+}
+
+impl Default for Limiter {
+    fn default() -> Self {
+        Limiter::new(RateLimit::default())
+    }
+}
+
+impl Limiter {
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        let burst = f64::from(limit.burst.max(1));
+        Limiter {
+            in_flight: limit.max_in_flight.map(Semaphore::new),
+            bucket: Mutex::new(Bucket {
+                rate: limit.requests_per_second,
+                burst,
+                tokens: burst,
+                last: Instant::now(),
+                paused_until: None,
+            }),
+        }
+    }
+
+    /// Waits until a request may be sent, the request is counted as in flight
+    /// until the returned permit is dropped
+    pub(crate) async fn acquire(&self) -> Option<SemaphorePermit<'_>> {
+        let permit = match &self.in_flight {
+            Some(semaphore) => Some(semaphore.acquire().await),
+            None => None,
+        };
+        let wait = self.bucket.lock().unwrap().reserve(Instant::now());
+        let mut reservation = Reservation {
+            bucket: &self.bucket,
+            used: false,
+        };
+        if wait > Duration::from_secs(0) {
+            delay_for(wait).await;
+        }
+        reservation.used = true;
+        permit
+    }
+
+    /// Holds back every request to this endpoint for the provided duration
+    pub(crate) fn pause(&self, duration: Duration) {
+        let until = Instant::now() + duration;
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.paused_until = Some(bucket.paused_until.map_or(until, |p| p.max(until)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{ContactBuilder, RetryPolicy};
+    use crate::jsonrpc::transport::mock::MockTransport;
+    use actix::System;
+    use futures::future::join_all;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_rate_limited_requests() {
+        let transport = Arc::new(MockTransport::default());
+        transport.rate_limit("http://a/syncing", Duration::from_secs(1));
+        transport.respond("http://a/syncing", 200, r#"{"syncing":false}"#);
+        let contact = ContactBuilder::new("http://a", Duration::from_secs(5))
+            .transport(transport.clone())
+            .retry_policy(RetryPolicy {
+                base_delay: Duration::from_millis(1),
+                ..Default::default()
+            })
+            .rate_limit(RateLimit {
+                max_in_flight: Some(1),
+                requests_per_second: Some(10.0),
+                burst: 1,
+            })
+            .build()
+            .unwrap();
+
+        System::new("test").block_on(async move {
+            // the retry has to wait for the Retry-After the node sent
+            let start = Instant::now();
+            assert!(!contact.get_syncing_status().await.unwrap().syncing);
+            assert!(Instant::now() - start >= Duration::from_secs(1));
+            assert_eq!(transport.requests.lock().unwrap().len(), 2);
+
+            // requests queue rather than fail once the rate is exceeded
+            let start = Instant::now();
+            let requests = (0..3).map(|_| contact.get_syncing_status());
+            assert!(join_all(requests).await.iter().all(|res| res.is_ok()));
+            assert!(Instant::now() - start >= Duration::from_millis(200));
+        });
+    }
+
+    #[test]
+    fn test_dropped_request_refunds_token() {
+        let limiter = Limiter::new(RateLimit {
+            max_in_flight: None,
+            requests_per_second: Some(1.0),
+            burst: 1,
+        });
+
+        System::new("test").block_on(async move {
+            limiter.acquire().await;
+            // this request gives up while waiting for the next token
+            let res = tokio::time::timeout(Duration::from_millis(10), limiter.acquire()).await;
+            assert!(res.is_err());
+            // so the request after it only waits for one token, not two
+            let wait = limiter.bucket.lock().unwrap().reserve(Instant::now());
+            assert!(wait <= Duration::from_secs(1));
+        });
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let start = Instant::now();
+        let mut bucket = Bucket {
+            rate: Some(2.0),
+            burst: 2.0,
+            tokens: 2.0,
+            last: start,
+            paused_until: None,
+        };
+        // the burst goes out immediately then requests queue at 2 per second
+        assert_eq!(bucket.reserve(start), Duration::from_secs(0));
+        assert_eq!(bucket.reserve(start), Duration::from_secs(0));
+        assert_eq!(bucket.reserve(start), Duration::from_millis(500));
+        assert_eq!(bucket.reserve(start), Duration::from_millis(1000));
+        // after a second the two queued tokens have been paid back
+        let later = start + Duration::from_secs(1);
+        assert_eq!(bucket.reserve(later), Duration::from_millis(500));
+
+        bucket.paused_until = Some(later + Duration::from_secs(5));
+        assert_eq!(bucket.reserve(later), Duration::from_secs(5));
+    }
+}
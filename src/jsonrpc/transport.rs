@@ -0,0 +1,297 @@
+use crate::jsonrpc::error::TransportError;
+use async_trait::async_trait;
+#[cfg(feature = "actix")]
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(not(any(feature = "actix", feature = "hyper-transport")))]
+compile_error!("Either the actix or the hyper-transport feature must be enabled");
+
+/// The transport used when the caller does not provide one, actix when that
+/// feature is enabled for compatibility, hyper otherwise
+#[cfg(feature = "actix")]
+pub type DefaultTransport = crate::jsonrpc::actix_transport::ActixTransport;
+#[cfg(all(feature = "hyper-transport", not(feature = "actix")))]
+pub type DefaultTransport = crate::jsonrpc::hyper_transport::HyperTransport;
+
+/// Connection level settings used to construct the built in transports
+#[derive(Debug, Clone, Default)]
+pub struct TransportConfig {
+    /// an additional PEM encoded root certificate to trust, for nodes
+    /// behind a private certificate authority
+    pub root_certificate: Option<Vec<u8>>,
+    /// a certificate to identify ourselves with to the server
+    pub client_certificate: Option<ClientCertificate>,
+    /// an http(s) proxy url to send all requests through, credentials in the
+    /// url are used to authenticate with the proxy
+    pub proxy: Option<String>,
+    /// the maximum number of connections, for hyper this is the maximum
+    /// number of idle connections kept open to each host
+    pub max_connections: Option<usize>,
+    /// how long an idle connection is kept open for reuse
+    pub idle_timeout: Option<Duration>,
+}
+
+/// A PEM encoded client certificate and its PEM encoded PKCS#8 private key
+#[derive(Debug, Clone)]
+pub struct ClientCertificate {
+    pub certificate: Vec<u8>,
+    pub private_key: Vec<u8>,
+}
+
+/// The raw result of a request made by a Transport, the status code
+/// and the full response body. Interpreting these is left to HTTPClient
+#[derive(Debug, Clone)]
+pub struct TransportResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+    /// the Retry-After header, sent by rate limited nodes, see parse_retry_after
+    pub retry_after: Option<Duration>,
+}
+
+impl TransportResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Parses the value of a Retry-After header, only the delay in seconds form
+/// is supported as nodes don't use the http date form in practice
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+/// Everything Contact holds must be Send and Sync for its futures to be Send,
+/// the actix transport is neither so this only applies without it
+#[cfg(feature = "actix")]
+pub trait MaybeSendSync {}
+#[cfg(feature = "actix")]
+impl<T: ?Sized> MaybeSendSync for T {}
+
+/// Everything Contact holds must be Send and Sync for its futures to be Send,
+/// the actix transport is neither so this only applies without it
+#[cfg(not(feature = "actix"))]
+pub trait MaybeSendSync: Send + Sync {}
+#[cfg(not(feature = "actix"))]
+impl<T: Send + Sync + ?Sized> MaybeSendSync for T {}
+
+/// The HTTP layer that HTTPClient is built on. Implement this to plug a
+/// different HTTP library, an in memory mock, or a recording proxy into
+/// Contact without changing any of the request handling logic.
+///
+/// `headers` must be added to the request, these are used for things like
+/// authentication. `limit` is the maximum response body size in bytes,
+/// implementations should return TransportError::Overflow as soon as a body
+/// passes it rather than reading the rest
+#[cfg_attr(feature = "actix", async_trait(?Send))]
+#[cfg_attr(not(feature = "actix"), async_trait)]
+pub trait Transport: MaybeSendSync {
+    async fn get(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        timeout: Duration,
+        limit: usize,
+    ) -> Result<TransportResponse, TransportError>;
+
+    /// Posts `body`, which is already serialized json, to the provided url
+    async fn post_json(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        body: Vec<u8>,
+        timeout: Duration,
+        limit: usize,
+    ) -> Result<TransportResponse, TransportError>;
+}
+
+/// Allows a caller to keep a handle to a transport after handing it to Contact
+#[cfg_attr(feature = "actix", async_trait(?Send))]
+#[cfg_attr(not(feature = "actix"), async_trait)]
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    async fn get(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        timeout: Duration,
+        limit: usize,
+    ) -> Result<TransportResponse, TransportError> {
+        (**self).get(url, headers, timeout, limit).await
+    }
+
+    async fn post_json(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        body: Vec<u8>,
+        timeout: Duration,
+        limit: usize,
+    ) -> Result<TransportResponse, TransportError> {
+        (**self).post_json(url, headers, body, timeout, limit).await
+    }
+}
+
+#[cfg(feature = "actix")]
+#[async_trait(?Send)]
+impl<T: Transport + ?Sized> Transport for Rc<T> {
+    async fn get(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        timeout: Duration,
+        limit: usize,
+    ) -> Result<TransportResponse, TransportError> {
+        (**self).get(url, headers, timeout, limit).await
+    }
+
+    async fn post_json(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        body: Vec<u8>,
+        timeout: Duration,
+        limit: usize,
+    ) -> Result<TransportResponse, TransportError> {
+        (**self).post_json(url, headers, body, timeout, limit).await
+    }
+}
+
+#[cfg_attr(feature = "actix", async_trait(?Send))]
+#[cfg_attr(not(feature = "actix"), async_trait)]
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    async fn get(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        timeout: Duration,
+        limit: usize,
+    ) -> Result<TransportResponse, TransportError> {
+        (**self).get(url, headers, timeout, limit).await
+    }
+
+    async fn post_json(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        body: Vec<u8>,
+        timeout: Duration,
+        limit: usize,
+    ) -> Result<TransportResponse, TransportError> {
+        (**self).post_json(url, headers, body, timeout, limit).await
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    use super::*;
+    use crate::client::{Contact, ContactBuilder, RetryPolicy};
+    use std::collections::{HashMap, VecDeque};
+    use std::sync::Mutex;
+
+    /// A Contact for tests that sends its requests to `urls` through `transport`,
+    /// making each request exactly once so scripted failures aren't retried away
+    pub(crate) fn test_contact<T: Transport + 'static>(urls: &[&str], transport: T) -> Contact {
+        let mut builder = ContactBuilder::new(urls[0], Duration::from_secs(1))
+            .transport(transport)
+            .retry_policy(RetryPolicy::never());
+        for url in &urls[1..] {
+            builder = builder.endpoint(url);
+        }
+        builder.build().unwrap()
+    }
+
+    /// A scripted transport for tests, responses are queued per url and
+    /// the last response for a url is repeated once the queue runs dry.
+    /// Scripted timeouts take the full timeout of the request to fail
+    #[derive(Default)]
+    pub(crate) struct MockTransport {
+        responses: Mutex<HashMap<String, VecDeque<Result<TransportResponse, TransportError>>>>,
+        pub(crate) requests: Mutex<Vec<String>>,
+        pub(crate) headers: Mutex<Vec<Vec<(String, String)>>>,
+    }
+
+    impl MockTransport {
+        pub(crate) fn respond(&self, url: &str, status: u16, body: &str) {
+            self.push(
+                url,
+                Ok(TransportResponse {
+                    status,
+                    body: body.as_bytes().to_vec(),
+                    retry_after: None,
+                }),
+            )
+        }
+
+        pub(crate) fn rate_limit(&self, url: &str, retry_after: Duration) {
+            self.push(
+                url,
+                Ok(TransportResponse {
+                    status: 429,
+                    body: Vec::new(),
+                    retry_after: Some(retry_after),
+                }),
+            )
+        }
+
+        pub(crate) fn fail(&self, url: &str, error: TransportError) {
+            self.push(url, Err(error))
+        }
+
+        fn push(&self, url: &str, res: Result<TransportResponse, TransportError>) {
+            self.responses
+                .lock()
+                .unwrap()
+                .entry(url.to_string())
+                .or_default()
+                .push_back(res)
+        }
+    }
+
+    #[cfg_attr(feature = "actix", async_trait(?Send))]
+    #[cfg_attr(not(feature = "actix"), async_trait)]
+    impl Transport for MockTransport {
+        async fn get(
+            &self,
+            url: &str,
+            headers: &[(String, String)],
+            timeout: Duration,
+            limit: usize,
+        ) -> Result<TransportResponse, TransportError> {
+            self.requests.lock().unwrap().push(url.to_string());
+            self.headers.lock().unwrap().push(headers.to_vec());
+            let res = {
+                let mut responses = self.responses.lock().unwrap();
+                let queue = match responses.get_mut(url) {
+                    Some(queue) => queue,
+                    None => return Err(TransportError::Connect(format!("no mock for {}", url))),
+                };
+                if queue.len() > 1 {
+                    queue.pop_front().unwrap()
+                } else {
+                    queue.front().unwrap().clone()
+                }
+            };
+            match res {
+                Ok(res) if res.body.len() > limit => Err(TransportError::Overflow),
+                // like a node that never answers
+                Err(TransportError::Timeout) => {
+                    tokio::time::delay_for(timeout).await;
+                    Err(TransportError::Timeout)
+                }
+                res => res,
+            }
+        }
+
+        async fn post_json(
+            &self,
+            url: &str,
+            headers: &[(String, String)],
+            _body: Vec<u8>,
+            timeout: Duration,
+            limit: usize,
+        ) -> Result<TransportResponse, TransportError> {
+            self.get(url, headers, timeout, limit).await
+        }
+    }
+}
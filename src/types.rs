@@ -23,28 +23,105 @@ pub struct TypeWrapper<T> {
     pub value: T,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+/// A public key in an account, Cosmos SDK 0.39 nodes send a bech32 string
+/// and 0.40+ nodes send a typed object, both decode to this
+#[derive(Serialize, Debug, Clone, Default)]
 pub struct PubKeyWrapper {
     #[serde(rename = "type")]
     key_type: String,
-    #[serde(deserialize_with = "parse_val")]
     value: PublicKey,
 }
 
+impl<'de> Deserialize<'de> for PubKeyWrapper {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Typed {
+                #[serde(rename = "type")]
+                key_type: String,
+                value: String,
+            },
+            Bech32(String),
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::Typed { key_type, value } => Ok(PubKeyWrapper {
+                key_type,
+                value: value.parse().map_err(de::Error::custom)?,
+            }),
+            // the prefix depends on the chain so FromStr can't be used here
+            Repr::Bech32(value) => Ok(PubKeyWrapper {
+                key_type: "tendermint/PubKeySecp256k1".to_string(),
+                value: PublicKey::from_bech32(value).map_err(de::Error::custom)?,
+            }),
+        }
+    }
+}
+
 fn default_account_number() -> u64 {
     0
 }
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+
+/// Decodes from a base account, or from the vesting and module accounts that
+/// Cosmos SDK 0.40+ nodes nest a base account inside of
+#[derive(Serialize, Debug, Default, Clone)]
 pub struct CosmosAccountInfo {
-    #[serde(deserialize_with = "parse_val")]
     pub address: Address,
     pub public_key: Option<PubKeyWrapper>,
-    #[serde(deserialize_with = "parse_val", default = "default_account_number")]
     pub sequence: u64,
-    #[serde(default = "default_account_number", deserialize_with = "parse_val")]
     pub account_number: u64,
 }
 
+#[derive(Deserialize)]
+struct BaseAccount {
+    #[serde(deserialize_with = "parse_val")]
+    address: Address,
+    #[serde(default)]
+    public_key: Option<PubKeyWrapper>,
+    #[serde(deserialize_with = "parse_val", default = "default_account_number")]
+    sequence: u64,
+    #[serde(default = "default_account_number", deserialize_with = "parse_val")]
+    account_number: u64,
+}
+
+impl<'de> Deserialize<'de> for CosmosAccountInfo {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct BaseVestingAccount {
+            base_account: BaseAccount,
+        }
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Base(BaseAccount),
+            Module {
+                base_account: BaseAccount,
+            },
+            Vesting {
+                base_vesting_account: BaseVestingAccount,
+            },
+        }
+        let base = match Repr::deserialize(deserializer)? {
+            Repr::Base(base) | Repr::Module { base_account: base } => base,
+            Repr::Vesting {
+                base_vesting_account,
+            } => base_vesting_account.base_account,
+        };
+        Ok(CosmosAccountInfo {
+            address: base.address,
+            public_key: base.public_key,
+            sequence: base.sequence,
+            account_number: base.account_number,
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct BlockId {
     pub hash: String,
@@ -53,6 +130,7 @@ pub struct BlockId {
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct BlockParts {
+    #[serde(deserialize_with = "parse_num")]
     pub total: u64,
     pub hash: String,
 }
@@ -61,6 +139,8 @@ pub struct BlockParts {
 pub struct BlockHeader {
     pub version: BlockVersion,
     pub chain_id: String,
+    #[serde(deserialize_with = "parse_val")]
+    pub height: u64,
     pub time: String,
     pub last_block_id: BlockId,
     pub last_commit_hash: String,
@@ -77,7 +157,7 @@ pub struct BlockHeader {
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct BlockVersion {
-    #[serde(deserialize_with = "parse_val")]
+    #[serde(deserialize_with = "parse_num")]
     pub block: u64,
 }
 
@@ -95,6 +175,12 @@ pub struct Block {
     pub last_commit: LastCommit,
 }
 
+impl Block {
+    pub fn height(&self) -> u64 {
+        self.header.height
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct BlockData {
     pub txs: Option<Vec<String>>,
@@ -109,6 +195,7 @@ pub struct BlockEvidence {
 pub struct LastCommit {
     #[serde(deserialize_with = "parse_val")]
     pub height: u64,
+    #[serde(deserialize_with = "parse_num")]
     pub round: u64,
     pub block_id: BlockId,
     pub signatures: Vec<BlockSignature>,
@@ -116,6 +203,7 @@ pub struct LastCommit {
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct BlockSignature {
+    #[serde(deserialize_with = "parse_num")]
     pub block_id_flag: u64,
     #[serde(deserialize_with = "parse_val_option")]
     pub validator_address: Option<Address>,
@@ -130,17 +218,103 @@ pub struct OptionalTXInfo {
     pub sequence: u64,
 }
 
+/// The validators active at a block height, from the validatorsets endpoints
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ValidatorSet {
+    #[serde(deserialize_with = "parse_val")]
+    pub block_height: u64,
+    pub validators: Vec<Validator>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Validator {
+    /// the consensus address, bech32 with the chain's valcons prefix
+    #[serde(deserialize_with = "parse_any_address")]
+    pub address: Address,
+    pub pub_key: ConsensusPubKey,
+    #[serde(deserialize_with = "parse_val")]
+    pub voting_power: u64,
+    #[serde(deserialize_with = "parse_val")]
+    pub proposer_priority: i64,
+}
+
+/// A validator's consensus public key, usually ed25519. Cosmos SDK 0.39 nodes
+/// send a bech32 string and 0.40+ nodes send a typed object
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ConsensusPubKey {
+    Typed {
+        #[serde(rename = "type")]
+        key_type: String,
+        /// the base64 encoded key
+        value: String,
+    },
+    Bech32(String),
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct SyncingStatus {
     pub syncing: bool,
 }
 
+/// The response of the node_info endpoint
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct NodeInfoResponse {
+    /// default_node_info when read from the gRPC gateway
+    #[serde(alias = "default_node_info")]
+    pub node_info: NodeInfo,
+    #[serde(default)]
+    pub application_version: ApplicationVersion,
+}
+
+impl NodeInfoResponse {
+    /// The chain id the node is on
+    pub fn network(&self) -> &str {
+        &self.node_info.network
+    }
+}
+
+/// The Tendermint node info of an LCD server's node
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct NodeInfo {
+    #[serde(alias = "default_node_id")]
+    pub id: String,
+    pub listen_addr: String,
+    /// the chain id
+    pub network: String,
+    /// the Tendermint version
+    pub version: String,
+    pub moniker: String,
+}
+
+/// The version of the Cosmos SDK application behind an LCD server
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ApplicationVersion {
+    /// the name of the application, for example gaia
+    #[serde(default)]
+    pub name: String,
+    /// the version of the application itself
+    #[serde(default)]
+    pub version: String,
+    #[serde(default, alias = "git_commit")]
+    pub commit: String,
+    /// only reported by Cosmos SDK 0.40 and later
+    #[serde(default)]
+    pub cosmos_sdk_version: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct TXSendResponse {
     pub logs: Option<Value>,
     pub txhash: String,
 }
 
+/// The response to txs/encode, the amino encoding of a transaction in base64
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct EncodeTxResponse {
+    pub tx: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct TxSendErrorResponse {
     pub code: u64,
@@ -180,6 +354,37 @@ where
     }
 }
 
+/// Parses an address as hex or as bech32 with any prefix, Address::from_str
+/// only accepts the cosmos prefix
+fn parse_any_address<'de, D>(deserializer: D) -> Result<Address, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = String::deserialize(deserializer)?;
+    match s.parse() {
+        Ok(address) => Ok(address),
+        Err(_) => Address::from_bech32(s).map_err(de::Error::custom),
+    }
+}
+
+/// Parses a number that may or may not be quoted, Cosmos SDK 0.39 nodes quote
+/// some numbers that 0.40+ nodes don't
+fn parse_num<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Number(u64),
+        Quoted(String),
+    }
+    match Repr::deserialize(deserializer)? {
+        Repr::Number(val) => Ok(val),
+        Repr::Quoted(s) => s.parse().map_err(de::Error::custom),
+    }
+}
+
 /// A blank struct, used to parse blank responses
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct Blank {}
@@ -212,9 +417,74 @@ mod tests {
         let _decoded: LatestBlockEndpointResponse = serde_json::from_str(&file).unwrap();
     }
 
+    #[test]
+    fn decode_account_info_both_formats() {
+        let key: PublicKey = "A7p7wCkHW3UY6Lp0y0ccExTdHM1dfeQYWkQRECnVFUQw"
+            .parse()
+            .unwrap();
+        // Cosmos SDK 0.39 sends the key as a bech32 string, this fixture follows
+        // the 0.39 amino encoding as no 0.39 node was available to capture from
+        let launchpad = include_str!("../test_files/account_info_launchpad.json");
+        // Cosmos SDK 0.40+ sends a typed key and nests the base account of
+        // vesting accounts, account_info_active.json is a plain base account
+        let stargate = include_str!("../test_files/account_info_stargate.json");
+        for file in &[launchpad, stargate] {
+            let decoded: ResponseWrapper<TypeWrapper<CosmosAccountInfo>> =
+                serde_json::from_str(file).unwrap();
+            let info = decoded.result.value;
+            assert_eq!(info.account_number, 4);
+            assert_eq!(info.public_key.unwrap().value, key);
+        }
+
+        let active = include_str!("../test_files/account_info_active.json");
+        let decoded: ResponseWrapper<TypeWrapper<CosmosAccountInfo>> =
+            serde_json::from_str(active).unwrap();
+        assert_eq!(decoded.result.value.public_key.unwrap().value, key);
+        assert_eq!(decoded.result.value.sequence, 10);
+    }
+
+    #[test]
+    fn decode_block_both_formats() {
+        // captured from althea-testnet1v5, block protocol 11 is Tendermint 0.34
+        // which Cosmos SDK 0.40+ runs on
+        let stargate = include_str!("../test_files/test_block_althea_testnet1v5.json");
+        // the same block as Tendermint 0.33, which Cosmos SDK 0.39 runs on, encodes
+        // it with amino json, block protocol 10 and quoted numbers
+        let launchpad = include_str!("../test_files/block_launchpad.json");
+        for file in &[stargate, launchpad] {
+            let decoded: LatestBlockEndpointResponse = serde_json::from_str(file).unwrap();
+            let block = decoded.block.unwrap();
+            assert_eq!(block.height(), 489421);
+            assert_eq!(block.height(), block.last_commit.height + 1);
+            assert_eq!(block.last_commit.block_id.parts.total, 1);
+
+            // a malformed height fails the decode rather than being dropped
+            let malformed = file.replacen(r#""height": "489421""#, r#""height": "latest""#, 1);
+            assert_ne!(&malformed, file);
+            assert!(serde_json::from_str::<LatestBlockEndpointResponse>(&malformed).is_err());
+        }
+    }
+
+    #[test]
+    fn decode_validator_set() {
+        let file =
+            read_to_string("test_files/validatorset.json").expect("Failed to read test files!");
+        let decoded: ResponseWrapper<ValidatorSet> = serde_json::from_str(&file).unwrap();
+        let validators = decoded.result.validators;
+        assert_eq!(decoded.result.block_height, 489421);
+        assert_eq!(validators[0].proposer_priority, -1250);
+        assert_eq!(validators[1].voting_power, 1000);
+        assert!(matches!(validators[1].pub_key, ConsensusPubKey::Bech32(_)));
+        // the proposer of the block at the same height
+        assert_eq!(
+            validators[1].address,
+            "7DD5CEA1B3DAA4E21929C1C93AE291BED0CF14B3".parse().unwrap()
+        );
+    }
+
     #[test]
     fn decode_starting_block() {
         let val = r#"{"block_id":{"hash":"","parts":{"total":0,"hash":""}},"block":null}"#;
-        let _decoded: LatestBlockEndpointResponse = serde_json::from_str(&val).unwrap();
+        let _decoded: LatestBlockEndpointResponse = serde_json::from_str(val).unwrap();
     }
 }